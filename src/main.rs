@@ -1,8 +1,13 @@
 mod model;
+mod simple_pdf;
 
-use clap::{Parser, Subcommand};
+use anyhow::Context as _;
+use clap::{Args, Parser, Subcommand};
 use comfy_table::{Cell, Table, Attribute, Color};
-use inquire::{Confirm, DateSelect, Select, Text};
+use inquire::validator::Validation;
+use inquire::{Confirm, CustomUserError, DateSelect, InquireError, Select, Text};
+use image::Luma;
+use qrcode::QrCode;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use slug::slugify;
@@ -11,11 +16,74 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tera::{Context, Tera};
-use zipcodes;
 use chrono::{Datelike, Local, NaiveDate};
 use directories::{BaseDirs, ProjectDirs};
 
-use crate::model::{ClientConfig, Address, Project, InvoiceItem, InvoiceContext, SenderConfig};
+use crate::model::{is_us_country, parse_hex_color, round_cents, RoundingMode, DEFAULT_ACCENT_COLOR, SCHEMA_VERSION, ClientConfig, Address, Project, Discount, RecurringInvoice, InvoiceItem, StatementContext, StatementLine, InvoiceContext, InvoiceRecord, InvoiceStatus, Payment, SecondTax, SenderConfig, SmtpConfig, TaxLine};
+
+// ==========================================
+// Console Output
+// ==========================================
+
+// All user-facing output goes through say! / say_err!, so `--plain` (or NO_COLOR)
+// can drop the emoji prefixes for logs, CI and screen readers.
+static PLAIN_OUTPUT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+macro_rules! say {
+    () => { println!() };
+    ($($arg:tt)*) => { println!("{}", plain_text(&format!($($arg)*))) };
+}
+
+macro_rules! say_err {
+    ($($arg:tt)*) => { eprintln!("{}", plain_text(&format!($($arg)*))) };
+}
+
+fn plain_output() -> bool {
+    PLAIN_OUTPUT.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+// Emoji and pictographs used as message prefixes (✅ ❌ ⚠️ 🔨 ⏸️ ℹ️ ...)
+fn is_decoration(c: char) -> bool {
+    matches!(c as u32,
+        0x2139 | 0x200D | 0xFE0F
+        | 0x2300..=0x23FF
+        | 0x2600..=0x27BF
+        | 0x2B00..=0x2BFF
+        | 0x1F000..=0x1FAFF)
+}
+
+// In plain mode, strip the decoration at the start of each line (plus the
+// spaces after it); ❌ and ⚠️ become "Error:" and "Warning:" so they keep their
+// meaning. Indentation and everything else is left alone.
+fn plain_text(text: &str) -> String {
+    if !plain_output() {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| {
+            let body = line.trim_start();
+            let indent = &line[..line.len() - body.len()];
+            let stripped = body.trim_start_matches(is_decoration);
+            let prefix = match body.chars().next() {
+                _ if stripped.len() == body.len() => return line.to_string(),
+                Some('❌') => "Error: ",
+                Some('⚠') => "Warning: ",
+                _ => "",
+            };
+            format!("{}{}{}", indent, prefix, stripped.trim_start())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// comfy-table without colors or bold in plain mode
+fn new_table() -> Table {
+    let mut table = Table::new();
+    if plain_output() {
+        table.force_no_tty();
+    }
+    table
+}
 
 // ==========================================
 // Constants & Embeds
@@ -23,8 +91,22 @@ use crate::model::{ClientConfig, Address, Project, InvoiceItem, InvoiceContext,
 const NEW_CLIENT_OPT: &str = "➕ Add New Client";
 const NEW_PROJECT_OPT: &str = "➕ Add New Project";
 
+// Filename / ID prefixes; the invoice prefix is configurable via `invoice_prefix`
+const DEFAULT_INVOICE_PREFIX: &str = "INV";
+
+// <invoice_id>_<project_id>, the filename before filename_pattern existed
+const DEFAULT_FILENAME_PATTERN: &str = "{id}_{project}";
+
+// output/<year>/<client_id>/, the layout before output_layout existed
+const DEFAULT_OUTPUT_LAYOUT: &str = "{year}/{client}";
+const ESTIMATE_PREFIX: &str = "QU";
+const CREDIT_PREFIX: &str = "CR";
+
 // Embed template at compile time to ensure availability
 const DEFAULT_TEMPLATE: &str = include_str!("../templates/invoice.tera");
+const DEFAULT_TEMPLATE_NAME: &str = "invoice.tera";
+const DEFAULT_STATEMENT_TEMPLATE: &str = include_str!("../templates/statement.tera");
+const STATEMENT_TEMPLATE_NAME: &str = "statement.tera";
 
 // ==========================================
 // Structs & Enums
@@ -32,12 +114,239 @@ const DEFAULT_TEMPLATE: &str = include_str!("../templates/invoice.tera");
 
 #[derive(Debug, Serialize, Deserialize)]
 struct AppSettings {
+    #[serde(default)]
+    schema_version: u32,
     data_root: String,
+    // Skip the `.lock` file, e.g. for filesystems without lock support
+    #[serde(default)]
+    disable_lock: bool,
+    // Prefix of regular invoice IDs, e.g. "INV" -> INV20251214-01
+    #[serde(default = "default_invoice_prefix")]
+    invoice_prefix: String,
+    // Typst executable; None runs `typst` from PATH
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typst_path: Option<String>,
+    // Reveal generated PDFs in the file browser; off for headless / SSH use
+    #[serde(default = "default_auto_open")]
+    auto_open: bool,
+    // Copy each generated invoice's PDF path to the clipboard
+    #[serde(default)]
+    copy_path: bool,
+    // Folders under output/ for new invoices: {year}, {month} and {client}; "" is flat
+    #[serde(default = "default_output_layout")]
+    output_layout: String,
+    // Invoice file names: {id}, {client}, {project} and {date}; must contain {id}
+    #[serde(default = "default_filename_pattern")]
+    filename_pattern: String,
+    // Tax rate % entered on the last invoice, offered as the default next time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_tax_rate: Option<f64>,
+}
+
+impl AppSettings {
+    // Defaults for everything but the data root, for runs with --data-dir and no settings.toml
+    fn for_root(data_root: String) -> AppSettings {
+        AppSettings {
+            schema_version: SCHEMA_VERSION,
+            data_root,
+            disable_lock: false,
+            invoice_prefix: default_invoice_prefix(),
+            typst_path: None,
+            auto_open: default_auto_open(),
+            copy_path: false,
+            output_layout: default_output_layout(),
+            filename_pattern: default_filename_pattern(),
+            last_tax_rate: None,
+        }
+    }
+}
+
+// Overrides `data_root` for a single run, below the --data-dir flag
+const DATA_DIR_ENV: &str = "INVOICE_MAKER_DATA_DIR";
+
+// Data root for this run: --data-dir, then $INVOICE_MAKER_DATA_DIR, then settings.toml
+fn with_data_root_override(settings: Option<AppSettings>, flag: Option<&str>) -> Option<AppSettings> {
+    let env = std::env::var(DATA_DIR_ENV).ok();
+    let Some(data_root) = flag.or(env.as_deref()).map(str::trim).filter(|d| !d.is_empty()) else { return settings };
+    Some(match settings {
+        Some(settings) => AppSettings { data_root: data_root.to_string(), ..settings },
+        None => AppSettings::for_root(data_root.to_string()),
+    })
+}
+
+fn default_filename_pattern() -> String {
+    DEFAULT_FILENAME_PATTERN.to_string()
+}
+
+fn default_output_layout() -> String {
+    DEFAULT_OUTPUT_LAYOUT.to_string()
+}
+
+fn default_auto_open() -> bool {
+    true
+}
+
+// Set once from AppSettings at startup, read by every Typst invocation
+static TYPST_BIN: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+// Cleared at startup by `--no-open` or `auto_open = false`; checked by open_and_reveal
+static AUTO_OPEN: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+
+// Set at startup by `--copy-path` or `copy_path = true`; checked by copy_path_to_clipboard
+static COPY_PATH: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+// output_layout and filename_pattern from AppSettings, set at startup
+static OUTPUT_LAYOUT: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+static FILENAME_PATTERN: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+// Sender's home country, set at startup; pre-fills the Country prompt of new addresses
+static HOME_COUNTRY: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+fn typst_command() -> Command {
+    Command::new(TYPST_BIN.get().map(String::as_str).unwrap_or("typst"))
+}
+
+fn default_invoice_prefix() -> String {
+    DEFAULT_INVOICE_PREFIX.to_string()
+}
+
+// Per-invoice choices collected by the New wizard (or CLI flags)
+#[derive(Serialize, Deserialize)]
+struct InvoiceInput {
+    items: Vec<InvoiceItem>,
+    date: NaiveDate,
+    tax_rate: f64,
+    tax_status: String,
+    // Optional second tax on top of `tax_rate` (only when tax is added)
+    second_tax: Option<SecondTax>,
+    // Item prices already include the tax; it is backed out for display instead of added
+    tax_inclusive: bool,
+    discount: Option<Discount>,
+    terms: String,
+    due_date: Option<NaiveDate>,
+    copy_labels: Vec<String>,
+    is_estimate: bool,
+    credit_for: Option<String>,
+    notes: Option<String>,
+    // Template file under templates/; None renders invoice.tera
+    template: Option<String>,
+}
+
+// Search filters; with none given, Search is interactive
+#[derive(Args)]
+struct SearchArgs {
+    /// Client name or ID contains this text (case-insensitive)
+    #[arg(long)]
+    client: Option<String>,
+    /// Minimum invoice total
+    #[arg(long)]
+    min: Option<f64>,
+    /// Maximum invoice total
+    #[arg(long)]
+    max: Option<f64>,
+    /// Invoice ID contains this text, e.g. 202503
+    #[arg(long)]
+    id: Option<String>,
+}
+
+impl SearchArgs {
+    fn is_empty(&self) -> bool {
+        self.client.is_none() && self.min.is_none() && self.max.is_none() && self.id.is_none()
+    }
+}
+
+#[derive(Args)]
+struct SummaryArgs {
+    /// Year to summarize (defaults to current year)
+    year: Option<i32>,
+    /// Compare each month against the previous year
+    #[arg(long)]
+    compare: bool,
+    /// Group by quarter (Q1-Q4) instead of by month
+    #[arg(long)]
+    quarterly: bool,
+    /// Only include invoices for this client ID
+    #[arg(long)]
+    client: Option<String>,
+    /// Print the tables as GitHub-flavored Markdown
+    #[arg(long)]
+    markdown: bool,
+    /// Also write the monthly and client breakdowns to a CSV file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+    /// Print the aggregated totals as JSON instead of tables
+    #[arg(long, conflicts_with = "markdown")]
+    json: bool,
+    /// Include invoices moved into output/archive/ by the Archive command
+    #[arg(long)]
+    archived: bool,
+}
+
+#[derive(Args)]
+struct TaxReportArgs {
+    /// Year to report (defaults to current year)
+    year: Option<i32>,
+    /// Also write the monthly and per-rate breakdowns to a CSV file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+}
+
+// Flags shared by New and Quote
+#[derive(Args)]
+struct NewArgs {
+    /// Print labeled copies into one PDF, e.g. --copies "CUSTOMER COPY,OFFICE COPY"
+    #[arg(long, value_delimiter = ',')]
+    copies: Vec<String>,
+    /// Client ID (skips client selection)
+    #[arg(long)]
+    client: Option<String>,
+    /// Project ID (skips project selection)
+    #[arg(long)]
+    project: Option<String>,
+    /// Line item as "desc:qty:rate" or "desc:amount" (repeatable, skips item entry)
+    #[arg(long, value_parser = parse_item_arg)]
+    item: Vec<InvoiceItem>,
+    /// Tax rate in percent, 0 for exempt (skips the tax prompt)
+    #[arg(long, value_parser = parse_tax_arg)]
+    tax: Option<f64>,
+    /// Payment terms in days, e.g. 30 for "Net 30" (skips the terms prompt)
+    #[arg(long)]
+    terms: Option<u32>,
+    /// Discount as "10%" or a flat amount like "50" (skips the discount prompt)
+    #[arg(long, value_parser = parse_discount_arg)]
+    discount: Option<Discount>,
+    /// Note printed above the payment information (skips the notes prompt)
+    #[arg(long)]
+    notes: Option<String>,
+    /// Invoice date as YYYY-MM-DD (skips the date prompt)
+    #[arg(long)]
+    date: Option<NaiveDate>,
+    /// Sender profile from senders/<name>.toml (skips the sender prompt)
+    #[arg(long)]
+    sender: Option<String>,
+    /// Template from templates/, e.g. "simple" or "simple.tera" (skips the template prompt)
+    #[arg(long)]
+    template: Option<String>,
+    /// Render a proof PDF watermarked "DRAFT" under drafts/, without using up an invoice number
+    #[arg(long)]
+    proof: bool,
 }
 
 #[derive(Parser)]
 #[command(name = "invoice-maker")]
 struct Cli {
+    /// Don't open generated PDFs, just print their path (for servers and scripts)
+    #[arg(long, global = true)]
+    no_open: bool,
+    /// Plain output without emoji or colors (also enabled by the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    plain: bool,
+    /// Copy the generated PDF's path to the clipboard
+    #[arg(long, global = true)]
+    copy_path: bool,
+    /// Data root for this run only, overriding settings.toml (also $INVOICE_MAKER_DATA_DIR)
+    #[arg(long, global = true, value_name = "PATH")]
+    data_dir: Option<String>,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -45,166 +354,392 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Create a new invoice
-    New,
+    New(NewArgs),
+    /// Create an estimate / quote (QU prefix, excluded from summaries)
+    Quote(NewArgs),
+    /// Save an invoice as a draft without using up an invoice number
+    Draft(NewArgs),
+    /// Turn a saved draft into a numbered invoice
+    Finalize,
     /// Add a new client
     AddClient,
+    /// Create clients from a CSV with columns name,email,street,city,state,zip (and optionally street2, country)
+    ImportClients {
+        /// CSV file to import (first row is the header)
+        #[arg(long)]
+        file: PathBuf,
+    },
+    /// Edit a client's name, contact, email and billing address
+    EditClient,
+    /// Edit a project's name, address and tax rate (its ID stays the same)
+    EditProject,
+    /// Edit your business details, bank info and invoice formatting (sender.toml)
+    EditSender,
+    /// Delete a client record (invoices in output/ are kept)
+    DeleteClient,
+    /// Rename a client and its ID, moving its invoice folders under output/ along
+    RenameClient,
+    /// List clients with their ID, email and number of projects
+    ListClients,
     /// Configure data directory
-    Config,
+    Config {
+        /// Print the current settings and resolved paths instead of running the wizard
+        #[arg(long)]
+        show: bool,
+    },
+    /// Check Typst, the data directory, sender.toml and templates for problems
+    Doctor,
     /// Mark invoice as PAID (hides already paid)
     Pay,
     /// Revert invoice to UNPAID (hides unpaid)
     Unpay,
-    /// List all PAID invoices
-    Paid,
-    /// List all UNPAID invoices
-    Unpaid,
+    /// Record a (partial) payment; marks the invoice PAID once the balance is zero
+    Payment,
+    /// Email an unpaid invoice PDF to the client (needs [smtp] in sender.toml)
+    Send,
+    /// Record that an unpaid invoice was sent to the client (without emailing it)
+    MarkSent,
+    /// List all PAID invoices, newest first
+    Paid {
+        /// Show only the N most recent invoices
+        #[arg(long, value_parser = parse_limit_arg)]
+        limit: Option<usize>,
+    },
+    /// List all UNPAID invoices, newest first
+    Unpaid {
+        /// Show only the N most recent invoices
+        #[arg(long, value_parser = parse_limit_arg)]
+        limit: Option<usize>,
+    },
     /// Open output folder
     Open,
+    /// Pick an invoice PDF and open it
+    OpenInvoice,
     /// Show summary of invoices
-    Summary {
-        /// Year to summarize (defaults to current year)
-        year: Option<i32>,
-    },
+    Summary(SummaryArgs),
+    /// Total the tax charged in a year, by month and by tax rate
+    TaxReport(TaxReportArgs),
+    /// Generate the recurring invoices due this month (defined under [[recurring]] in info.toml)
+    Recurring,
+    /// List unpaid invoices past their due date, most overdue first
+    Overdue,
+    /// Show unpaid balances per client in Current / 1-30 / 31-60 / 61-90 / 90+ day buckets
+    Aging,
+    /// Render a statement PDF of a client's invoices and outstanding balance
+    Statement,
     /// Search invoices (type to filter by path, client, project, description, amount)
-    Search,
+    Search(SearchArgs),
     /// Void an invoice
     Void,
+    /// Undo a void, restoring the invoice to PAID or UNPAID
+    Unvoid,
+    /// Edit the items of an existing invoice
+    Edit,
+    /// Issue a credit note (negative amounts) against an existing invoice
+    Credit,
+    /// Create a new invoice from an existing one (dated today)
+    Duplicate,
+    /// Delete an invoice (.typ and .pdf)
+    Delete {
+        /// Also allow deleting PAID invoices
+        #[arg(long)]
+        force: bool,
+    },
+    /// Upgrade config files to the current schema and legacy invoices: regenerate .typ files lacking is_paid / is_void or a sidecar
+    Migrate,
+    /// Recompile every invoice .typ under output/ to PDF, in parallel
+    RecompileAll {
+        /// First re-render each .typ from its sidecar with the current template
+        #[arg(long)]
+        render: bool,
+    },
+    /// Move a year's invoices into output/archive/<year>.zip (summary --archived still reads them)
+    Archive {
+        /// Year of the invoices to archive, by invoice date
+        #[arg(long)]
+        year: i32,
+    },
+    /// Zip the whole data root into a timestamped archive
+    Backup {
+        /// Archive file or directory to write to (defaults to the current directory)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Extract a Backup archive into the data root
+    Restore {
+        /// Backup archive (.zip) to restore
+        #[arg(long)]
+        file: PathBuf,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+    },
     /// Check for updates and update the binary
     Update,
 }
 
+impl Commands {
+    // Commands that write client data or invoices and must hold the data lock
+    fn mutates_data(&self) -> bool {
+        matches!(
+            self,
+            Commands::New(_) | Commands::Quote(_) | Commands::Draft(_) | Commands::Finalize | Commands::AddClient | Commands::ImportClients { .. } | Commands::EditClient | Commands::EditProject | Commands::EditSender | Commands::DeleteClient | Commands::RenameClient | Commands::Recurring | Commands::Pay | Commands::Unpay | Commands::Payment | Commands::Send | Commands::MarkSent | Commands::Void | Commands::Unvoid | Commands::Statement
+                | Commands::Restore { .. } | Commands::Edit | Commands::Credit | Commands::Duplicate | Commands::Delete { .. } | Commands::Migrate | Commands::RecompileAll { .. } | Commands::Archive { .. }
+        )
+    }
+}
+
 // ==========================================
 // Main Function
 // ==========================================
 
 fn main() {
+    if let Err(e) = run() {
+        // Esc / Ctrl-C inside a prompt is not an error
+        if matches!(e.downcast_ref::<InquireError>(), Some(InquireError::OperationCanceled | InquireError::OperationInterrupted)) {
+            say!("⏸️  Operation cancelled.");
+            return;
+        }
+        say_err!("❌ {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    if cli.plain || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        PLAIN_OUTPUT.store(true, std::sync::atomic::Ordering::Relaxed);
+        inquire::set_global_render_config(inquire::ui::RenderConfig::empty());
+    }
+
+    // Works before the first-run wizard, to debug where things are
+    if let Some(Commands::Config { show: true }) = &cli.command {
+        show_config(with_data_root_override(load_settings(), cli.data_dir.as_deref()).as_ref());
+        return Ok(());
+    }
+    if let Some(Commands::Doctor) = &cli.command {
+        run_doctor(cli.data_dir.as_deref());
+        return Ok(());
+    }
     
-    // 1. Initialize configuration
-    let settings = load_settings().unwrap_or_else(|| setup_config_wizard());
+    // 1. Initialize configuration; an overridden data root needs no first-run wizard
+    let settings = match with_data_root_override(load_settings(), cli.data_dir.as_deref()) {
+        Some(settings) => settings,
+        None => setup_config_wizard()?,
+    };
     let expanded_path = expand_home_dir(&settings.data_root);
     let root = PathBuf::from(expanded_path);
+    if let Some(typst) = &settings.typst_path {
+        TYPST_BIN.set(expand_home_dir(typst)).ok();
+    }
+    if cli.no_open || !settings.auto_open {
+        AUTO_OPEN.store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+    OUTPUT_LAYOUT.set(settings.output_layout.clone()).ok();
+    // Status changes and sidecars find invoices by the ID in the filename
+    if settings.filename_pattern.contains("{id}") {
+        FILENAME_PATTERN.set(settings.filename_pattern.clone()).ok();
+    } else {
+        say!("⚠️  filename_pattern {:?} has no {{id}}, using {:?}.", settings.filename_pattern, DEFAULT_FILENAME_PATTERN);
+    }
+    if cli.copy_path || settings.copy_path {
+        COPY_PATH.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
     let data_dir = root.join("data/clients");
     
     // Ensure data directory exists
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("❌ Error: Failed to create data directory: {}", e);
-        return;
-    }
+    fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
 
     // Load sender configuration
-    let sender_config = load_sender_config(&root);
+    let sender_config = load_sender_config(&root)?;
+    if let Some(country) = &sender_config.country {
+        HOME_COUNTRY.set(country.clone()).ok();
+    }
 
     if cli.command.is_none() {
         use clap::CommandFactory;
-        Cli::command().print_help().unwrap();
-        return;
+        Cli::command().print_help()?;
+        return Ok(());
     }
 
-    match cli.command.unwrap() {
-        Commands::New => {
-            let client_id = select_or_create_client(&data_dir);
-            println!("✅ Selected Client: {}", client_id);
-
-            let (client_config, selected_project) = select_or_create_project(&data_dir, &client_id);
-            println!("✅ Selected Project: {} ({})", selected_project.name.as_deref().unwrap_or("No Name"), selected_project.address.street);
+    // Prevent concurrent writes (e.g. an overlapping scheduled run); held until exit
+    let _lock = if cli.command.as_ref().is_some_and(|c| c.mutates_data()) && !settings.disable_lock {
+        acquire_data_lock(&root)
+    } else {
+        None
+    };
 
-            let items = enter_invoice_items();
-            
-            if !items.is_empty() {
-                // Date selection
-                let date = DateSelect::new("Invoice Date:")
-                    .with_default(Local::now().date_naive())
-                    .prompt()
-                    .unwrap();
-
-                let (tax_rate, tax_status) = ask_for_tax();
-                
-                generate_pdf(&root, &client_id, &client_config, &selected_project, &items, tax_rate, date, tax_status, &sender_config);
-            } else {
-                println!("❌ No items entered. Aborting.");
-            }
+    match cli.command.unwrap() {
+        Commands::New(args) => {
+            create_document(&root, &data_dir, &sender_config, &settings.invoice_prefix, args, false, false)?;
+        }
+        Commands::Quote(args) => {
+            create_document(&root, &data_dir, &sender_config, &settings.invoice_prefix, args, true, false)?;
+        }
+        Commands::Draft(args) => {
+            create_document(&root, &data_dir, &sender_config, &settings.invoice_prefix, args, false, true)?;
+        }
+        Commands::Finalize => {
+            finalize_draft(&root, &data_dir, &sender_config, &settings.invoice_prefix)?;
         }
         Commands::AddClient => {
-            create_client_wizard(&data_dir);
+            create_client_wizard(&data_dir)?;
+        }
+        Commands::ImportClients { file } => {
+            import_clients(&data_dir, &file)?;
+        }
+        Commands::EditClient => {
+            edit_client_wizard(&data_dir)?;
+        }
+        Commands::EditProject => {
+            edit_project_wizard(&data_dir)?;
+        }
+        Commands::EditSender => {
+            edit_sender_wizard(&root)?;
+        }
+        Commands::DeleteClient => {
+            delete_client(&root, &data_dir);
+        }
+        Commands::RenameClient => {
+            rename_client(&root, &data_dir)?;
+        }
+        Commands::ListClients => {
+            list_clients(&data_dir);
+        }
+        Commands::Recurring => {
+            run_recurring(&root, &data_dir, &sender_config, &settings.invoice_prefix)?;
         }
-        Commands::Config => {
-            setup_config_wizard();
+        Commands::Config { .. } => {
+            setup_config_wizard()?;
         }
+        // Handled before the first-run wizard above
+        Commands::Doctor => unreachable!("doctor returns before setup"),
         Commands::Pay => {
-            // true = Mark as Paid (show only unpaid)
-            change_invoice_status(&root, true);
+            change_invoice_status(&root, InvoiceStatus::Paid, &sender_config)?;
         }
         Commands::Unpay => {
-            // false = Mark as Unpaid (show only paid)
-            change_invoice_status(&root, false);
+            change_invoice_status(&root, InvoiceStatus::Unpaid, &sender_config)?;
         }
-        Commands::Paid => {
-            list_invoices_by_status(&root, true);
+        Commands::Payment => {
+            record_payment(&root, &sender_config)?;
         }
-        Commands::Unpaid => {
-            list_invoices_by_status(&root, false);
+        Commands::MarkSent => {
+            mark_invoice_sent(&root, &sender_config)?;
+        }
+        Commands::Send => {
+            send_invoice(&root, &data_dir, &sender_config)?;
+        }
+        Commands::Paid { limit } => {
+            list_invoices_by_status(&root, InvoiceStatus::Paid, limit, &sender_config);
+        }
+        Commands::Unpaid { limit } => {
+            list_invoices_by_status(&root, InvoiceStatus::Unpaid, limit, &sender_config);
         }
         Commands::Open => {
             open_folder_wizard(&root);
         }
-        Commands::Search => {
+        Commands::OpenInvoice => {
+            open_invoice_wizard(&root);
+        }
+        Commands::Search(filters) if filters.is_empty() => {
             search_invoices(&root);
         }
-        Commands::Summary { year } => {
-            show_summary(&root, year);
+        Commands::Search(filters) => {
+            search_invoices_filtered(&root, &filters, &sender_config);
+        }
+        Commands::Summary(args) => {
+            show_summary(&root, &args, &sender_config);
+        }
+        Commands::TaxReport(args) => {
+            show_tax_report(&root, &args, &sender_config);
+        }
+        Commands::Overdue => {
+            show_overdue(&root, &sender_config);
+        }
+        Commands::Aging => {
+            show_aging(&root, &sender_config);
+        }
+        Commands::Statement => {
+            create_statement(&root, &data_dir, &sender_config)?;
         }
         Commands::Void => {
-            void_invoice(&root);
+            void_invoice(&root, &sender_config)?;
+        }
+        Commands::Unvoid => {
+            unvoid_invoice(&root, &sender_config)?;
+        }
+        Commands::Edit => {
+            edit_invoice(&root, &sender_config)?;
+        }
+        Commands::Credit => {
+            credit_invoice(&root, &sender_config, &settings.invoice_prefix)?;
+        }
+        Commands::Duplicate => {
+            duplicate_invoice(&root, &data_dir, &sender_config, &settings.invoice_prefix)?;
+        }
+        Commands::Delete { force } => {
+            delete_invoice(&root, force);
+        }
+        Commands::Migrate => {
+            migrate_configs(&root, &data_dir)?;
+            migrate_invoices(&root, &sender_config);
+        }
+        Commands::RecompileAll { render } => {
+            recompile_all(&root, &sender_config, render);
+        }
+        Commands::Archive { year } => {
+            archive_year(&root, year);
+        }
+        Commands::Backup { out } => {
+            backup_data_root(&root, out.as_deref());
+        }
+        Commands::Restore { file, force } => {
+            restore_backup(&root, &file, force);
         }
         Commands::Update => {
-            check_and_update();
+            check_and_update()?;
         }
     }
+    Ok(())
 }
 
 // ==========================================
 // 1. Client & Project Logic
 // ==========================================
 
-fn select_or_create_client(data_dir: &Path) -> String {
+fn select_or_create_client(data_dir: &Path) -> anyhow::Result<String> {
     let mut options = vec![NEW_CLIENT_OPT.to_string()];
     
     if let Ok(entries) = fs::read_dir(data_dir) {
         for entry in entries.flatten() {
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                if let Ok(name) = entry.file_name().into_string() {
-                    options.push(name);
-                }
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false)
+                && let Ok(name) = entry.file_name().into_string()
+            {
+                options.push(name);
             }
         }
     }
 
-    let ans = Select::new("Please Select Client (Type to Filter):", options).prompt();
+    let choice = Select::new("Please Select Client (Type to Filter):", options).prompt()?;
 
-    match ans {
-        Ok(choice) => {
-            if choice == NEW_CLIENT_OPT {
-                create_client_wizard(data_dir)
-            } else {
-                choice
-            }
-        },
-        Err(_) => std::process::exit(0),
+    if choice == NEW_CLIENT_OPT {
+        create_client_wizard(data_dir)
+    } else {
+        Ok(choice)
     }
 }
 
 // Create Client Wizard
-fn create_client_wizard(data_dir: &Path) -> String {
-    println!("\n--- Creating New Client ---");
+fn create_client_wizard(data_dir: &Path) -> anyhow::Result<String> {
+    say!("\n--- Creating New Client ---");
 
     // 1. Ask for Company Name (Optional)
-    let company_input = Text::new("Company Name (Optional, press Enter to skip):").prompt().unwrap();
+    let company_input = Text::new("Company Name (Optional, press Enter to skip):").prompt()?;
     let company = if company_input.trim().is_empty() { None } else { Some(company_input.trim().to_string()) };
 
     // 2. Adjust contact person prompt based on company presence
     let attn_prompt = if company.is_some() { "Attn / Contact Person:" } else { "Client Name:" };
-    let attn_input = Text::new(attn_prompt).prompt().unwrap();
+    let attn_input = Text::new(attn_prompt).prompt()?;
     
     // 3. Determine ID (prefer company slug, fallback to person slug)
     let raw_name_for_id = if let Some(c) = &company { c } else { &attn_input };
@@ -219,38 +754,407 @@ fn create_client_wizard(data_dir: &Path) -> String {
         (format!("Attn: {}", attn_input), None)
     };
 
-    let email_input = Text::new("Client Email (Optional):").prompt().unwrap();
+    let email_input = Text::new("Client Email (Optional):").prompt()?;
     let email = if email_input.trim().is_empty() { None } else { Some(email_input) };
 
-    println!("\n--- Enter Client Billing Address (Optional) ---");
-    let billing_address = wizard_address_new_order(true);
+    say!("\n--- Enter Client Billing Address (Optional) ---");
+    let billing_address = wizard_address_new_order(true)?;
+
+    let (tax_exempt, default_tax_rate) = ask_client_tax(false, None)?;
 
     let client = ClientConfig {
+        schema_version: SCHEMA_VERSION,
         name: final_name,
         attn: final_attn,
         email,
         billing_address,
         projects: vec![],
+        recurring: vec![],
+        default_tax_rate,
+        tax_exempt,
     };
 
     let client_path = data_dir.join(&id);
     if client_path.exists() {
-        println!("⚠️  Client ID {} already exists, using existing folder.", id);
+        say!("⚠️  Client ID {} already exists, using existing folder.", id);
     } else {
-        fs::create_dir_all(&client_path).expect("Creating client directory failed");
+        fs::create_dir_all(&client_path).with_context(|| format!("Failed to create {:?}", client_path))?;
     }
     
-    let toml_str = toml::to_string_pretty(&client).unwrap();
-    fs::write(client_path.join("info.toml"), toml_str).expect("Failed to write info.toml");
+    save_client_config(data_dir, &id, &client)?;
+
+    say!("✅ Client created successfully: {}", id);
+    Ok(id)
+}
+
+// Client tax treatment: exempt, or an optional default rate (percent) for new invoices
+fn ask_client_tax(exempt: bool, default_rate: Option<f64>) -> anyhow::Result<(bool, Option<f64>)> {
+    let exempt = Confirm::new("Tax-exempt client (e.g. reseller)?").with_default(exempt).prompt()?;
+    if exempt {
+        return Ok((true, None));
+    }
+    let current_rate = default_rate.map(|r| r.to_string()).unwrap_or_default();
+    let rate_input = Text::new("Default Tax Rate % (Optional, e.g. 8.875):")
+        .with_default(&current_rate)
+        .with_validator(validate_optional_tax_rate)
+        .prompt()?;
+    Ok((false, parse_tax_arg(&rate_input).ok()))
+}
+
+// One row of an ImportClients CSV
+#[derive(Deserialize)]
+struct ClientRow {
+    name: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    street: String,
+    #[serde(default)]
+    street2: String,
+    #[serde(default)]
+    city: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    zip: String,
+    #[serde(default)]
+    country: String,
+}
+
+fn import_clients(data_dir: &Path, file: &Path) -> anyhow::Result<()> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(file)
+        .with_context(|| format!("Failed to open {:?}", file))?;
+
+    let (mut created, mut skipped) = (0, 0);
+    for (line, row) in reader.deserialize::<ClientRow>().enumerate() {
+        let row = match row {
+            Ok(r) => r,
+            Err(e) => { say!("⚠️  Row {}: {}, skipping.", line + 2, e); skipped += 1; continue; }
+        };
+        if row.name.is_empty() {
+            say!("⚠️  Row {}: missing name, skipping.", line + 2);
+            skipped += 1;
+            continue;
+        }
+
+        let id = slugify(&row.name);
+        let client_path = data_dir.join(&id);
+        if client_path.exists() {
+            say!("⚠️  Client ID {} already exists, skipping.", id);
+            skipped += 1;
+            continue;
+        }
+
+        let billing_address = (!row.street.is_empty()).then_some(Address {
+            street: row.street,
+            street2: (!row.street2.is_empty()).then_some(row.street2),
+            city: row.city,
+            state: row.state,
+            zip: row.zip,
+            country: (!row.country.is_empty()).then_some(row.country),
+        });
+        let client = ClientConfig {
+            schema_version: SCHEMA_VERSION,
+            name: row.name,
+            attn: None,
+            email: (!row.email.is_empty()).then_some(row.email),
+            billing_address,
+            projects: vec![],
+            recurring: vec![],
+            default_tax_rate: None,
+            tax_exempt: false,
+        };
+
+        fs::create_dir_all(&client_path).with_context(|| format!("Failed to create {:?}", client_path))?;
+        save_client_config(data_dir, &id, &client)?;
+        say!("✅ Client created: {}", id);
+        created += 1;
+    }
+
+    say!("\nImported {} client(s), skipped {}.", created, skipped);
+    Ok(())
+}
+
+// Pick an existing client folder (no "Add New" entry)
+// Client IDs (folder names under data/clients), sorted
+fn client_ids(data_dir: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = fs::read_dir(data_dir)
+        .map(|entries| entries.flatten()
+            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+fn select_client(data_dir: &Path, prompt: &str) -> Option<String> {
+    let options = client_ids(data_dir);
+    if options.is_empty() {
+        say!("❌ No clients found.");
+        return None;
+    }
+
+    Select::new(prompt, options).prompt().ok()
+}
+
+// Edit Client Wizard: every prompt is pre-filled with the current value
+fn edit_client_wizard(data_dir: &Path) -> anyhow::Result<()> {
+    let Some(client_id) = select_client(data_dir, "Select Client to Edit:") else { return Ok(()) };
+    let mut config = load_client_config(data_dir, &client_id)?;
+
+    say!("\n--- Editing Client: {} ---", client_id);
+    say!("💡 Press Enter to keep a value, or type '-' to clear an optional field.");
+
+    let name = Text::new("Client Name:").with_default(&config.name).prompt()?;
+    if !name.trim().is_empty() {
+        config.name = name.trim().to_string();
+    }
+
+    config.attn = prompt_optional("Attn / Contact Person (Optional):", config.attn.as_deref())?;
+    config.email = prompt_optional("Client Email (Optional):", config.email.as_deref())?;
+
+    let edit_address = Confirm::new("Edit billing address?").with_default(false).prompt()?;
+    if edit_address {
+        say!("\n--- Client Billing Address (type '-' as Street to remove) ---");
+        config.billing_address = wizard_address_edit(config.billing_address.as_ref(), true)?;
+    }
+
+    (config.tax_exempt, config.default_tax_rate) = ask_client_tax(config.tax_exempt, config.default_tax_rate)?;
+
+    save_client_config(data_dir, &client_id, &config)?;
+    say!("✅ Client updated: {}", client_id);
+    Ok(())
+}
+
+// Edit Project Wizard. The project id is kept so invoice filenames still match.
+fn edit_project_wizard(data_dir: &Path) -> anyhow::Result<()> {
+    let Some(client_id) = select_client(data_dir, "Select Client:") else { return Ok(()) };
+    let mut config = load_client_config(data_dir, &client_id)?;
+
+    if config.projects.is_empty() {
+        say!("❌ Client '{}' has no projects.", client_id);
+        return Ok(());
+    }
+
+    let options: Vec<String> = config.projects.iter()
+        .map(|p| format!("{} | {} ({})", p.name.as_deref().unwrap_or("Project"), p.address.street, p.id))
+        .collect();
+    let index = Select::new("Select Project to Edit:", options).raw_prompt()?.index;
+    let project = &mut config.projects[index];
+
+    say!("\n--- Editing Project: {} ---", project.id);
+    say!("💡 Press Enter to keep a value, or type '-' to clear an optional field.");
+
+    project.name = prompt_optional("Project Name (Optional):", project.name.as_deref())?;
+
+    say!("--- Project Address ---");
+    if let Some(address) = wizard_address_edit(Some(&project.address), false)? {
+        project.address = address;
+    }
+
+    let current_rate = project.tax_rate.map(|r| r.to_string()).unwrap_or_default();
+    let rate_input = Text::new("Project Tax Rate % (Optional, e.g. 8.875):")
+        .with_default(&current_rate)
+        .with_validator(validate_optional_tax_rate)
+        .prompt()?;
+    project.tax_rate = parse_tax_arg(&rate_input).ok();
+
+    let project_id = project.id.clone();
+    save_client_config(data_dir, &client_id, &config)?;
+    say!("✅ Project updated: {}", project_id);
+    Ok(())
+}
+
+// Read-only overview of data/clients, to find the slug for --client
+fn list_clients(data_dir: &Path) {
+    let ids = client_ids(data_dir);
+    if ids.is_empty() {
+        say!("❌ No clients found.");
+        return;
+    }
+
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("ID"),
+        Cell::new("Name"),
+        Cell::new("Email"),
+        Cell::new("Projects"),
+    ]);
+    for id in &ids {
+        match load_client_config(data_dir, id) {
+            Ok(config) => table.add_row(vec![
+                Cell::new(id),
+                Cell::new(&config.name),
+                Cell::new(config.email.as_deref().unwrap_or("")),
+                Cell::new(config.projects.len()),
+            ]),
+            Err(_) => table.add_row(vec![
+                Cell::new(id),
+                Cell::new("⚠️  unreadable info.toml").fg(Color::Red),
+                Cell::new(""),
+                Cell::new(""),
+            ]),
+        };
+    }
+    say!("{table}");
+}
+
+// Move `data/clients/<old>` and every output/ folder named after the client (e.g.
+// `output/<year>/<old>`) to the new ID, and update the client ID in the sidecars.
+fn rename_client(root: &Path, data_dir: &Path) -> anyhow::Result<()> {
+    let Some(old_id) = select_client(data_dir, "Select Client to RENAME:") else { return Ok(()) };
+    let mut config = load_client_config(data_dir, &old_id)?;
+
+    let name = Text::new("New Client Name:").with_default(&config.name).prompt()?;
+    let name = if name.trim().is_empty() { config.name.clone() } else { name.trim().to_string() };
+
+    let id_input = Text::new("New Client ID:").with_default(&slugify(&name)).prompt()?;
+    let new_id = slugify(id_input.trim());
+    if new_id.is_empty() {
+        say!("❌ Client ID cannot be empty.");
+        return Ok(());
+    }
+    if new_id != old_id && data_dir.join(&new_id).exists() {
+        say!("❌ Client ID {} already exists.", new_id);
+        return Ok(());
+    }
+
+    // Folders named after the client (the {client} part of the layout), outermost first
+    let output_dir = root.join("output");
+    let invoices = client_invoices(&output_dir, &old_id);
+    let client_folders: std::collections::BTreeSet<PathBuf> = invoices.iter()
+        .filter_map(|p| {
+            let relative = p.parent()?.strip_prefix(&output_dir).ok()?;
+            let mut dir = output_dir.clone();
+            for part in relative.components() {
+                dir.push(part);
+                if part.as_os_str() == old_id.as_str() {
+                    return Some(dir);
+                }
+            }
+            None
+        })
+        .collect();
+    let folders: Vec<(PathBuf, PathBuf)> = client_folders.into_iter()
+        .map(|dir| { let to = dir.with_file_name(&new_id); (dir, to) })
+        .collect();
+    if new_id != old_id
+        && let Some((_, taken)) = folders.iter().find(|(_, to)| to.exists())
+    {
+        say!("❌ {:?} already exists, aborting.", taken);
+        return Ok(());
+    }
+
+    let mut preview = Vec::new();
+    if name != config.name {
+        preview.push(format!("Rename client '{}' to '{}'", config.name, name));
+    }
+    if new_id != old_id {
+        preview.push(format!("Move data/clients/{} to data/clients/{}", old_id, new_id));
+        for (from, to) in &folders {
+            preview.push(format!("Move {} to {}",
+                from.strip_prefix(root).unwrap_or(from).display(),
+                to.strip_prefix(root).unwrap_or(to).display()));
+        }
+    }
+    if preview.is_empty() {
+        say!("ℹ️  Nothing to change.");
+        return Ok(());
+    }
+    // Nothing is deleted, so there is nothing to snapshot
+    if !confirm_destructive(root, &preview, &[]) {
+        return Ok(());
+    }
+
+    if new_id != old_id {
+        fs::rename(data_dir.join(&old_id), data_dir.join(&new_id))
+            .with_context(|| format!("Failed to move data/clients/{}", old_id))?;
+        say!("📁 Moved: data/clients/{} -> data/clients/{}", old_id, new_id);
+
+        for (from, to) in &folders {
+            fs::rename(from, to).with_context(|| format!("Failed to move {:?}", from))?;
+            say!("📁 Moved: {} -> {}",
+                from.strip_prefix(root).unwrap_or(from).display(),
+                to.strip_prefix(root).unwrap_or(to).display());
+        }
+
+        // Sidecars record the client ID too
+        for invoice in &invoices {
+            let moved = folders.iter()
+                .find_map(|(from, to)| Some(to.join(invoice.strip_prefix(from).ok()?)))
+                .unwrap_or_else(|| invoice.clone());
+            let Some(mut record) = read_sidecar(&moved) else { continue };
+            record.client_id = new_id.clone();
+            write_sidecar(&moved, &record)?;
+        }
+    }
+
+    config.name = name;
+    save_client_config(data_dir, &new_id, &config)?;
+    say!("✅ Client renamed: {}", new_id);
+    Ok(())
+}
+
+// Remove `data/clients/<id>`. Billing history under output/ is never deleted,
+// but a client with invoices needs its ID typed out to confirm.
+fn delete_client(root: &Path, data_dir: &Path) {
+    let Some(client_id) = select_client(data_dir, "Select Client to DELETE:") else { return };
+    let client_dir = data_dir.join(&client_id);
+
+    let invoices = client_invoices(&root.join("output"), &client_id);
 
-    println!("✅ Client created successfully: {}", id);
-    id
+    if !invoices.is_empty() {
+        say!("\n⚠️  Client '{}' has {} invoice(s) under output/.", client_id, invoices.len());
+        say!("   They will be kept, but can no longer be edited, duplicated or credited.");
+        let typed = Text::new(&format!("Type '{}' to confirm:", client_id)).prompt().unwrap_or_default();
+        if typed.trim() != client_id {
+            say!("⏸️  Operation cancelled.");
+            return;
+        }
+    }
+
+    let preview = vec![format!("Delete client folder data/clients/{}", client_id)];
+    if !confirm_destructive(root, &preview, std::slice::from_ref(&client_dir)) {
+        return;
+    }
+
+    match fs::remove_dir_all(&client_dir) {
+        Ok(_) => say!("🗑️  Deleted client: {}", client_id),
+        Err(e) => say_err!("❌ Failed to delete {}: {}", client_id, e),
+    }
 }
 
-fn select_or_create_project(data_dir: &Path, client_id: &str) -> (ClientConfig, Project) {
+// Text prompt for an optional field; Enter keeps `current`, "-" clears it
+fn prompt_optional(prompt: &str, current: Option<&str>) -> anyhow::Result<Option<String>> {
+    let input = Text::new(prompt).with_default(current.unwrap_or("")).prompt()?;
+    Ok(match input.trim() {
+        "" | "-" => None,
+        value => Some(value.to_string()),
+    })
+}
+
+fn save_client_config(data_dir: &Path, client_id: &str, config: &ClientConfig) -> anyhow::Result<()> {
+    let config_path = data_dir.join(client_id).join("info.toml");
+    let toml_str = toml::to_string_pretty(config)?;
+    fs::write(&config_path, toml_str).with_context(|| format!("Failed to write {:?}", config_path))
+}
+
+fn load_client_config(data_dir: &Path, client_id: &str) -> anyhow::Result<ClientConfig> {
     let config_path = data_dir.join(client_id).join("info.toml");
-    let content = fs::read_to_string(&config_path).expect("Failed to read client config");
-    let mut config: ClientConfig = toml::from_str(&content).expect("TOML parsing failed");
+    let content = fs::read_to_string(&config_path).with_context(|| format!("Failed to read {:?}", config_path))?;
+    let mut config: ClientConfig = toml::from_str(&content).with_context(|| format!("Invalid client config {:?}", config_path))?;
+    // Fields added since take their defaults; `migrate` writes them out
+    if schema_outdated(config.schema_version, &config_path) {
+        config.schema_version = SCHEMA_VERSION;
+    }
+    Ok(config)
+}
+
+fn select_or_create_project(data_dir: &Path, client_id: &str) -> anyhow::Result<(ClientConfig, Project)> {
+    let mut config = load_client_config(data_dir, client_id)?;
 
     let mut options = Vec::new();
     options.push(NEW_PROJECT_OPT.to_string());
@@ -260,999 +1164,4736 @@ fn select_or_create_project(data_dir: &Path, client_id: &str) -> (ClientConfig,
         options.push(format!("{} | {}", display_name, p.address.street));
     }
 
-    let ans = Select::new("Select Project / Job Site:", options).prompt().unwrap();
+    // Pick by position: several projects may share a street (or contain " | ")
+    let ans = Select::new("Select Project / Job Site:", options).raw_prompt()?;
 
-    if ans == NEW_PROJECT_OPT {
-        println!("\n--- Adding New Project ---");
+    if ans.index == 0 {
+        say!("\n--- Adding New Project ---");
         
-        let name_input = Text::new("Project Name (Optional):").prompt().unwrap();
+        let name_input = Text::new("Project Name (Optional):").prompt()?;
         let name = if name_input.trim().is_empty() { None } else { Some(name_input) };
         
-        println!("--- Enter Project Address ---");
+        say!("--- Enter Project Address ---");
         
         let address;
         let mut reused_billing = false;
         
         if let Some(billing) = &config.billing_address {
-            println!("Found Billing Address: {}, {}, {}", billing.street, billing.city, billing.state);
+            say!("Found Billing Address: {}, {}, {}", billing.street, billing.city, billing.state);
             let same = Confirm::new("Use same address as billing?")
                 .with_default(true)
-                .prompt()
-                .unwrap();
+                .prompt()?;
             
             if same {
                 address = billing.clone();
                 reused_billing = true;
             } else {
-                address = Address { street: "".into(), city: "".into(), state: "".into(), zip: "".into() };
+                address = Address { street: "".into(), street2: None, city: "".into(), state: "".into(), zip: "".into(), country: None };
             }
         } else {
-             address = Address { street: "".into(), city: "".into(), state: "".into(), zip: "".into() };
+             address = Address { street: "".into(), street2: None, city: "".into(), state: "".into(), zip: "".into(), country: None };
         }
 
         let final_address = if reused_billing {
             address
         } else {
-            wizard_address_new_order(false).expect("Project address is required!")
+            wizard_address_new_order(false)?.context("Project address is required")?
         };
 
-        let id = slugify(&final_address.street);
+        let id = unique_project_id(&config, &slugify(&final_address.street));
+
+        let tax_input = Text::new("Project Tax Rate % (Optional, e.g. 8.875):")
+            .with_validator(validate_optional_tax_rate)
+            .prompt()?;
+        let tax_rate = parse_tax_arg(&tax_input).ok();
 
         let new_project = Project {
             id,
             name,
             address: final_address,
+            tax_rate,
         };
 
         config.projects.push(new_project.clone());
-        let new_toml = toml::to_string_pretty(&config).unwrap();
-        fs::write(config_path, new_toml).expect("Failed to update info.toml");
+        save_client_config(data_dir, client_id, &config)?;
 
-        println!("✅ Project added to database!");
-        (config, new_project)
+        say!("✅ Project added to database! ID: {}", new_project.id);
+        Ok((config, new_project))
     } else {
-        let selected_street = ans.split(" | ").last().unwrap();
-        let project = config.projects.iter().find(|p| p.address.street == selected_street).unwrap().clone();
-        (config, project)
+        let project = config.projects[ans.index - 1].clone();
+        Ok((config, project))
+    }
+}
+
+// Project IDs end up in invoice filenames, so two projects on the same street
+// get "-2", "-3", ... instead of overwriting each other's invoices
+fn unique_project_id(config: &ClientConfig, slug: &str) -> String {
+    let taken = |id: &str| config.projects.iter().any(|p| p.id == id);
+    if !taken(slug) {
+        return slug.to_string();
     }
+    (2..).map(|n| format!("{}-{}", slug, n)).find(|id| !taken(id)).unwrap()
 }
 
 // ==========================================
 // 2. Data Entry Helpers
 // ==========================================
 
-fn wizard_address_new_order(is_optional: bool) -> Option<Address> {
+fn wizard_address_new_order(is_optional: bool) -> anyhow::Result<Option<Address>> {
     let street_prompt = if is_optional { "Street (Leave empty to skip):" } else { "Street (Required):" };
-    let street = Text::new(street_prompt).prompt().unwrap();
+    let street = Text::new(street_prompt).prompt()?;
 
     if is_optional && street.trim().is_empty() {
-        return None;
+        return Ok(None);
     }
+    let street2 = Text::new("Street Line 2 (Optional, e.g. Suite 200):").prompt()?;
+    let street2 = Some(street2.trim().to_string()).filter(|s| !s.is_empty());
 
-    let zip = Text::new("Zip Code (Leave empty to skip lookup):").prompt().unwrap();
-    let (mut def_city, mut def_state) = (String::new(), String::new());
+    let country = prompt_country(None)?;
+    let zip = Text::new("Zip / Postal Code (Leave empty to skip lookup):").prompt()?;
+    let (def_city, def_state) = lookup_us_zip(&zip, country.as_deref()).unwrap_or_default();
 
-    if !zip.trim().is_empty() {
-        match zipcodes::matching(&zip, None) {
-            Ok(results) => {
-                if let Some(info) = results.first() {
-                    println!("🚀 Found: {}, {}", info.city, info.state);
-                    def_city = info.city.to_string();
-                    def_state = info.state.to_string();
-                }
-            },
-            Err(_) => {}
+    let city = Text::new("City:").with_default(&def_city).prompt()?;
+    let state = Text::new(state_prompt(country.as_deref())).with_default(&def_state).prompt()?;
+
+    Ok(Some(Address { street, street2, city, state, zip, country }))
+}
+
+// City/state for a US ZIP. Other postal codes (Canadian, UK, EU, ...) are not looked up;
+// city and region are typed in instead.
+fn lookup_us_zip(zip: &str, country: Option<&str>) -> Option<(String, String)> {
+    let zip = zip.trim();
+    let us_zip = Regex::new(r"^\d{5}(-\d{4})?$").unwrap();
+    if zip.is_empty() || !is_us_country(country) || !us_zip.is_match(zip) {
+        return None;
+    }
+    match zipcodes::matching(&zip[..5], None).ok().and_then(|results| results.first().cloned()) {
+        Some(info) => {
+            say!("🚀 Found: {}, {}", info.city, info.state);
+            Some((info.city.to_string(), info.state.to_string()))
+        }
+        None => {
+            say!("ℹ️  ZIP {} not found, please enter the city and state.", zip);
+            None
         }
     }
+}
 
-    let city = Text::new("City:").with_default(&def_city).prompt().unwrap();
-    let state = Text::new("State:").with_default(&def_state).prompt().unwrap();
+fn state_prompt(country: Option<&str>) -> &'static str {
+    if is_us_country(country) { "State:" } else { "State / Province / Region:" }
+}
 
-    Some(Address { street, city, state, zip })
+// Country for an address; empty means domestic (no country line on the invoice)
+fn prompt_country(current: Option<&str>) -> anyhow::Result<Option<String>> {
+    let default = current.or(HOME_COUNTRY.get().map(String::as_str)).unwrap_or("");
+    let input = Text::new("Country (Leave empty for domestic):").with_default(default).prompt()?;
+    Ok(Some(input.trim().to_string()).filter(|c| !c.is_empty()))
 }
 
-// Returns (tax_rate, status_text)
-fn ask_for_tax() -> (f64, String) {
-    let apply_tax = Confirm::new("Add Tax to Total?").with_default(true).prompt().unwrap();
+// Address prompts pre-filled from `current`. With `is_optional`, an empty street returns None.
+fn wizard_address_edit(current: Option<&Address>, is_optional: bool) -> anyhow::Result<Option<Address>> {
+    let Some(current) = current else { return wizard_address_new_order(is_optional) };
+
+    let street = Text::new("Street:").with_default(&current.street).prompt()?;
+    if is_optional && street.trim() == "-" {
+        return Ok(None);
+    }
+    let street2 = prompt_optional("Street Line 2 (Optional):", current.street2.as_deref())?;
+
+    let country = prompt_country(current.country.as_deref())?;
+    let zip = Text::new("Zip / Postal Code:").with_default(&current.zip).prompt()?;
+
+    // Look the city/state up again only when the zip changed
+    let (def_city, def_state) = Some(&zip)
+        .filter(|zip| **zip != current.zip)
+        .and_then(|zip| lookup_us_zip(zip, country.as_deref()))
+        .unwrap_or_else(|| (current.city.clone(), current.state.clone()));
+
+    let city = Text::new("City:").with_default(&def_city).prompt()?;
+    let state = Text::new(state_prompt(country.as_deref())).with_default(&def_state).prompt()?;
+
+    Ok(Some(Address { street, street2, city, state, zip, country }))
+}
+
+// Tax answers of the New wizard
+struct TaxChoice {
+    rate: f64,
+    status: String,
+    second_tax: Option<SecondTax>,
+    inclusive: bool,
+    // Rate (in %) typed at the prompt; becomes the next default once the invoice is written
+    entered_rate: Option<f64>,
+}
+
+impl TaxChoice {
+    // Tax from a percent rate; 0 means exempt
+    fn from_percent(rate: f64, inclusive: bool) -> TaxChoice {
+        if rate > 0.0 {
+            TaxChoice { rate: rate / 100.0, status: "ADD".to_string(), second_tax: None, inclusive, entered_rate: None }
+        } else {
+            TaxChoice { rate: 0.0, status: "Exempt".to_string(), second_tax: None, inclusive: false, entered_rate: None }
+        }
+    }
+}
+
+// `default_rate` (percent) pre-fills the prompt, e.g. from the selected project
+fn ask_for_tax(default_rate: Option<f64>, sender: &SenderConfig) -> anyhow::Result<TaxChoice> {
+    let label = &sender.tax_label;
+    let apply_tax = Confirm::new(&format!("Add {} to Total?", label))
+        .with_default(default_rate.is_none_or(|r| r > 0.0))
+        .prompt()?;
     
     if apply_tax {
-        let rate_str = Text::new("Tax Rate % (e.g. 8.875):").with_default("8.875").prompt().unwrap();
-        let rate: f64 = rate_str.parse().unwrap_or(0.0);
+        // The project's rate wins over the one used last time
+        let default_str = default_rate
+            .or_else(|| load_settings().and_then(|s| s.last_tax_rate))
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| "8.875".to_string());
+        let rate_str = Text::new(&format!("{} Rate % (e.g. 8.875):", label))
+            .with_default(&default_str)
+            .with_validator(validate_tax_rate)
+            .prompt()?;
+        let rate = parse_tax_arg(&rate_str).map_err(anyhow::Error::msg)?;
+        let second_tax = ask_for_second_tax()?;
+        let inclusive = Confirm::new(&format!("Do the item prices already include {}?", label))
+            .with_default(sender.tax_inclusive)
+            .prompt()?;
         // If adding tax, return rate. Status text is generated later.
-        (rate / 100.0, "ADD".to_string()) 
+        Ok(TaxChoice { rate: rate / 100.0, status: "ADD".to_string(), second_tax, inclusive, entered_rate: Some(rate) })
     } else {
         // If not adding tax, ask for reason
         let options = vec!["Exempt", "Included"];
-        let status = Select::new("Tax Status:", options).prompt().unwrap();
-        (0.0, status.to_string())
+        let status = Select::new(&format!("{} Status:", label), options).prompt()?;
+        Ok(TaxChoice { rate: 0.0, status: status.to_string(), second_tax: None, inclusive: false, entered_rate: None })
+    }
+}
+
+// A second tax printed on its own line, e.g. NYC local tax on top of state tax
+fn ask_for_second_tax() -> anyhow::Result<Option<SecondTax>> {
+    let add = Confirm::new("Add a second tax (e.g. city / local tax)?")
+        .with_default(false)
+        .prompt()?;
+    if !add {
+        return Ok(None);
+    }
+    let label = Text::new("Second Tax Label:").with_default("Local Tax").prompt()?;
+    let rate_str = Text::new("Second Tax Rate % (e.g. 4.5):")
+        .with_validator(validate_tax_rate)
+        .prompt()?;
+    let rate = parse_tax_arg(&rate_str).map_err(anyhow::Error::msg)?;
+    Ok(Some(SecondTax { label: label.trim().to_string(), rate: rate / 100.0 }))
+}
+
+const TERM_DAYS: [u32; 4] = [15, 30, 45, 60];
+const DEFAULT_TERM_DAYS: u32 = 30;
+const CUSTOM_DUE_DATE_OPT: &str = "Custom due date";
+
+fn net_terms(date: NaiveDate, days: u32) -> (String, NaiveDate) {
+    (format!("Net {}", days), date + chrono::Duration::days(days as i64))
+}
+
+fn with_due((terms, due_date): (String, NaiveDate)) -> (String, Option<NaiveDate>) {
+    (terms, Some(due_date))
+}
+
+// Payment terms: Net 15/30/45/60 or an explicit due date (no terms label)
+fn ask_for_terms(date: NaiveDate) -> anyhow::Result<(String, NaiveDate)> {
+    let mut options: Vec<String> = TERM_DAYS.iter().map(|d| format!("Net {}", d)).collect();
+    options.push(CUSTOM_DUE_DATE_OPT.to_string());
+    let default_index = TERM_DAYS.iter().position(|d| *d == DEFAULT_TERM_DAYS).unwrap_or(0);
+
+    let choice = Select::new("Payment Terms:", options)
+        .with_starting_cursor(default_index)
+        .prompt()?;
+
+    match TERM_DAYS.iter().find(|d| format!("Net {}", d) == choice) {
+        Some(days) => Ok(net_terms(date, *days)),
+        None => {
+            let due = DateSelect::new("Due Date:")
+                .with_default(date + chrono::Duration::days(DEFAULT_TERM_DAYS as i64))
+                .with_min_date(date)
+                .prompt()?;
+            Ok((String::new(), due))
+        }
     }
 }
 
-fn enter_invoice_items() -> Vec<InvoiceItem> {
-    let mut items = Vec::new();
-    println!("\n--- Enter Invoice Items ---");
-    println!("💡 Tip: Use '\\n' for new lines, and '- ' for bullet points."); 
-    println!("(Leave Description empty to finish)");
+// Optional free-text note for this invoice
+fn ask_for_notes() -> anyhow::Result<Option<String>> {
+    let input = Text::new("Notes (Optional, use '\\n' for new lines):").prompt()?;
+    Ok(if input.trim().is_empty() { None } else { Some(input.trim().to_string()) })
+}
 
+// Optional discount, e.g. "10%" or "$50"
+fn ask_for_discount() -> anyhow::Result<Option<Discount>> {
     loop {
-        let desc = Text::new("Description (leave empty to finish):").prompt().unwrap();
-        
-        if desc.trim().is_empty() {
-            break;
+        let input = Text::new("Discount (e.g. 10% or $50, leave empty for none):").prompt()?;
+        if input.trim().is_empty() {
+            return Ok(None);
+        }
+        match Discount::parse(&input) {
+            Some(d) => return Ok(Some(d)),
+            None => say!("❌ Invalid discount. Use a percentage like 10% or an amount like 50."),
         }
+    }
+}
 
-        let amount_str = Text::new("Amount ($):").prompt().unwrap();
-        let amount: f64 = amount_str.parse().unwrap_or(0.0);
+// Lenient number parsing for typed or pasted amounts: "$1,250.00" -> 1250.0
+fn parse_amount(input: &str) -> Result<f64, std::num::ParseFloatError> {
+    let cleaned: String = input.chars().filter(|c| !c.is_whitespace() && *c != '$' && *c != ',').collect();
+    cleaned.parse()
+}
 
-        items.push(InvoiceItem {
-            description: desc,
-            quantity: 1.0,
-            rate: amount,
-            amount: amount,
-        });
+fn parse_tax_arg(arg: &str) -> Result<f64, String> {
+    let rate = parse_amount(arg.trim().trim_end_matches('%')).map_err(|_| format!("invalid tax rate '{}'", arg))?;
+    if (0.0..=100.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!("tax rate must be between 0 and 100, got {}", rate))
     }
-    items
 }
 
-// ==========================================
-// 3. PDF Generation (New Logic)
-// ==========================================
+// --limit 0 would print an empty table
+fn parse_limit_arg(arg: &str) -> Result<usize, String> {
+    match arg.trim().parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("limit must be a whole number of at least 1, got '{}'", arg)),
+    }
+}
 
-fn generate_pdf(
-    root: &Path, 
-    client_id: &str, 
-    client: &ClientConfig, 
-    project: &Project, 
-    items: &[InvoiceItem],
-    tax_rate: f64,
-    date: NaiveDate, // Date parameter
-    tax_status: String,
-    sender: &SenderConfig,
-) {
-    // Check if Typst is installed
-    if Command::new("typst").arg("--version").output().is_err() {
-        println!("❌ Error: 'typst' is not installed. Please install it (brew install typst).");
-        return;
+// Prompt validators: re-ask instead of silently turning bad input into 0
+fn validate_amount(input: &str) -> Result<Validation, CustomUserError> {
+    Ok(match parse_amount(input) {
+        Ok(n) if n.is_finite() && n >= 0.0 => Validation::Valid,
+        Ok(_) => Validation::Invalid("Must not be negative.".into()),
+        Err(_) => Validation::Invalid("Please enter a number, e.g. 12.50".into()),
+    })
+}
+
+fn validate_tax_rate(input: &str) -> Result<Validation, CustomUserError> {
+    Ok(match parse_tax_arg(input) {
+        Ok(_) => Validation::Valid,
+        Err(_) => Validation::Invalid("Please enter a rate between 0 and 100, e.g. 8.875".into()),
+    })
+}
+
+// Empty (or "-") leaves the rate unset
+fn validate_optional_tax_rate(input: &str) -> Result<Validation, CustomUserError> {
+    match input.trim() {
+        "" | "-" => Ok(Validation::Valid),
+        rate => validate_tax_rate(rate),
     }
+}
 
-    // Initialize template
-    let template_dir = root.join("templates");
-    if !template_dir.exists() { fs::create_dir_all(&template_dir).unwrap(); }
-    let template_path = template_dir.join("invoice.tera");
-    if !template_path.exists() { 
-        println!("✨ Initializing default template...");
-        fs::write(&template_path, DEFAULT_TEMPLATE).expect("Failed to write default template");
+fn parse_discount_arg(arg: &str) -> Result<Discount, String> {
+    Discount::parse(arg).ok_or_else(|| format!("invalid discount '{}', expected e.g. \"10%\" or \"50\"", arg))
+}
+
+// `existing` are lines already on the invoice; they count towards the running subtotal
+fn enter_invoice_items(sender: &SenderConfig, existing: &[InvoiceItem]) -> anyhow::Result<Vec<InvoiceItem>> {
+    let mut items: Vec<InvoiceItem> = Vec::new();
+    say!("\n--- Enter Invoice Items ---");
+    say!("💡 Tip: Use '\\n' for new lines, and '- ' for bullet points."); 
+    say!("(Leave Description empty to finish)");
+
+    loop {
+        match prompt_invoice_item(None, sender)? {
+            ItemPrompt::Item(item) => {
+                items.push(item);
+                let count = existing.len() + items.len();
+                let subtotal: f64 = existing.iter().chain(&items).map(|i| i.amount).sum();
+                say!("🧮 Subtotal: {} ({} item{})", sender.format_money(subtotal), count, if count == 1 { "" } else { "s" });
+            }
+            ItemPrompt::Discarded => continue,
+            ItemPrompt::Finished => break,
+        }
     }
+    Ok(items)
+}
 
-    let tera = match Tera::new(template_dir.join("*.tera").to_str().unwrap()) {
-        Ok(t) => t,
-        Err(e) => { println!("❌ Template Error: {}", e); return; }
-    };
+const GENERATE_OPT: &str = "✅ Generate";
+const ADD_MORE_OPT: &str = "➕ Add More";
+const CANCEL_OPT: &str = "❌ Cancel";
 
-    // Calculate totals
-    let total_before_tax: f64 = items.iter().map(|i| i.amount).sum();
-    let tax_amount = total_before_tax * tax_rate;
-    let total = total_before_tax + tax_amount;
+// Review screen before the PDF is generated; returns false when cancelled
+fn review_items(items: &mut Vec<InvoiceItem>, sender: &SenderConfig) -> anyhow::Result<bool> {
+    loop {
+        print_items_table("Review Items", items, sender);
 
-    let tax_display_str = if tax_rate > 0.0 {
-        format!("${:.2}", tax_amount) // Show amount if tax exists
-    } else {
-        tax_status // Show "Exempt" or "Included" if no tax
-    };
-    
-    // --- Invoice ID Generation (HI20251214-01) ---
-    let date_str = date.format("%Y%m%d").to_string(); // 20251214
-    let prefix = format!("HI{}", date_str); // HI20251214
-    
-    // Scan output directory for current year to find max index
-    let output_root = root.join("output");
-    let mut next_idx = 1;
+        let actions = if items.is_empty() {
+            vec![ADD_MORE_OPT, CANCEL_OPT]
+        } else {
+            vec![GENERATE_OPT, EDIT_ITEM_OPT, REMOVE_ITEM_OPT, ADD_MORE_OPT, CANCEL_OPT]
+        };
 
-    let year_dir = output_root.join(date.format("%Y").to_string());
-    if year_dir.exists() {
-        let mut stack = vec![year_dir];
-        while let Some(dir) = stack.pop() {
-             if let Ok(entries) = fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        stack.push(path);
-                    } else if let Some(fname) = path.file_name() {
-                        let fname_str = fname.to_string_lossy();
-                        if fname_str.starts_with(&prefix) {
-                            // Filename format: HI20251214-01_xxx.typ
-                            // Extract part after prefix
-                            let rest = &fname_str[prefix.len()..]; 
-                            if rest.starts_with("-") {
-                                // Parse index
-                                let num_part: String = rest.chars()
-                                    .skip(1) // Skip '-'
-                                    .take_while(|c| c.is_numeric())
-                                    .collect();
-                                if let Ok(idx) = num_part.parse::<u32>() {
-                                    if idx >= next_idx {
-                                        next_idx = idx + 1;
-                                    }
-                                }
-                            }
-                        }
-                    }
+        match Select::new("Ready to generate?", actions).prompt()? {
+            GENERATE_OPT => return Ok(true),
+            EDIT_ITEM_OPT => {
+                if let Some(idx) = select_item_index(items, "Select Item to Edit:")
+                    && let ItemPrompt::Item(item) = prompt_invoice_item(Some(&items[idx]), sender)?
+                {
+                    items[idx] = item;
                 }
-             }
+            }
+            REMOVE_ITEM_OPT => {
+                if let Some(idx) = select_item_index(items, "Select Item to Remove:") {
+                    let removed = items.remove(idx);
+                    say!("🗑️  Removed: {}", removed.description);
+                }
+            }
+            ADD_MORE_OPT => {
+                let more = enter_invoice_items(sender, items)?;
+                items.extend(more);
+            }
+            _ => return Ok(false),
         }
     }
+}
+
+// Line items with a running subtotal
+fn print_items_table(title: &str, items: &[InvoiceItem], sender: &SenderConfig) {
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("#"),
+        Cell::new("Description"),
+        Cell::new("Qty"),
+        Cell::new("Rate"),
+        Cell::new("Amount"),
+        Cell::new("Taxable"),
+        Cell::new("Subtotal"),
+    ]);
+
+    let mut subtotal = 0.0;
+    for (i, item) in items.iter().enumerate() {
+        subtotal += item.amount;
+        table.add_row(vec![
+            Cell::new(i + 1),
+            Cell::new(item.description.replace("\\n", "\n")),
+            Cell::new(item.quantity_label()),
+            Cell::new(sender.format_money(item.rate)),
+            Cell::new(sender.format_money(item.amount)),
+            Cell::new(if item.taxable { "Yes" } else { "No" }),
+            Cell::new(sender.format_money(subtotal)),
+        ]);
+    }
 
-    let invoice_id = format!("{}-{:02}", prefix, next_idx); // e.g., HI20251214-01s
+    say!("\n--- {} ---", title);
+    if items.is_empty() {
+        say!("(no items)");
+    } else {
+        say!("{table}");
+    }
+}
 
-    // Construct Context
-    let date_today = Local::now().date_naive();
+// Parse a --item argument: "desc:qty:rate" or "desc:amount" (desc may contain ':')
+fn parse_item_arg(arg: &str) -> Result<InvoiceItem, String> {
+    let parts: Vec<&str> = arg.rsplitn(3, ':').collect();
+    let parse_num = |s: &str| match parse_amount(s) {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(n),
+        Ok(_) => Err(format!("negative number '{}' in item '{}'", s, arg)),
+        Err(_) => Err(format!("invalid number '{}' in item '{}'", s, arg)),
+    };
 
-    let context_data = InvoiceContext {
-        id: invoice_id.clone(),
-        date: date_today.format("%m/%d/%Y").to_string(),
-        sender: sender.clone(),
-        client: client.clone(),
-        project: project.clone(),
-        items: items.to_vec(),
-        total,
-        tax_rate,
-        // Hardcoded Footer Content
-        is_void: false,
-        is_paid: false,
-        tax_display: tax_display_str,
+    let (description, quantity, rate) = match parts.as_slice() {
+        [rate, qty, desc] => match parse_num(qty) {
+            Ok(q) => (desc.to_string(), q, parse_num(rate)?),
+            // "a:b:10" where "a:b" is the description
+            Err(_) => (format!("{}:{}", desc, qty), 1.0, parse_num(rate)?),
+        },
+        [amount, desc] => (desc.to_string(), 1.0, parse_num(amount)?),
+        _ => return Err(format!("expected \"desc:qty:rate\" or \"desc:amount\", got '{}'", arg)),
     };
 
-    let context = Context::from_serialize(&context_data).unwrap();
-    let rendered = tera.render("invoice.tera", &context).unwrap();
+    if description.trim().is_empty() {
+        return Err(format!("missing description in item '{}'", arg));
+    }
 
-    let output_dir = output_root.join(date.format("%Y").to_string()).join(client_id);
-    fs::create_dir_all(&output_dir).unwrap();
+    let amount = (quantity * rate * 100.0).round() / 100.0;
+    Ok(InvoiceItem { description, quantity, rate, amount, taxable: true, unit: None })
+}
 
-    // Filename: HI20251214-01_ProjectID.pdf
-    let filename_base = format!("{}_{}", invoice_id, project.id);
-    let typ_path = output_dir.join(format!("{}.typ", filename_base));
-    let pdf_path = output_dir.join(format!("{}.pdf", filename_base));
+enum ItemPrompt {
+    Item(InvoiceItem),
+    Discarded,
+    Finished,
+}
 
-    fs::write(&typ_path, rendered).expect("Failed to write .typ file");
+// Prompt for a single line item. `current` pre-fills the prompts when editing.
+fn prompt_invoice_item(current: Option<&InvoiceItem>, sender: &SenderConfig) -> anyhow::Result<ItemPrompt> {
+    let mut desc_prompt = Text::new("Description (leave empty to finish):");
+    if let Some(item) = current {
+        desc_prompt = desc_prompt.with_default(&item.description);
+    }
+    let desc = desc_prompt.prompt()?;
+    
+    if desc.trim().is_empty() {
+        return Ok(ItemPrompt::Finished);
+    }
 
-    println!("\n🔨 Compiling PDF...");
-    match Command::new("typst").arg("compile").arg(&typ_path).arg(&pdf_path).status() {
-        Ok(s) if s.success() => {
-            println!("✅ PDF Generated: {:?}", pdf_path);
-            open_and_reveal(&pdf_path);
-        },
-        _ => println!("❌ Compilation failed."),
+    // Quantity defaults to 1 so a lump-sum amount can be entered as the rate
+    let qty_default = current.map(|i| i.quantity.to_string()).unwrap_or_else(|| "1".to_string());
+    let qty_str = Text::new("Quantity:").with_default(&qty_default).with_validator(validate_amount).prompt()?;
+    let quantity = parse_amount(&qty_str)?;
+    let unit = prompt_optional("Unit (Optional, e.g. hours, each, sq ft):", current.and_then(|i| i.unit.as_deref()))?;
+
+    let rate_default = current.map(|i| i.rate.to_string()).unwrap_or_default();
+    let rate_str = Text::new(&format!("Rate ({}):", sender.symbol().trim()))
+        .with_default(&rate_default)
+        .with_validator(validate_amount)
+        .prompt()?;
+    let rate = parse_amount(&rate_str)?;
+
+    let amount = (quantity * rate * 100.0).round() / 100.0;
+    let mut item = InvoiceItem { description: desc, quantity, rate, amount, taxable: true, unit };
+    say!("   = {} x {} = {}", item.quantity_label(), sender.format_money(rate), sender.format_money(amount));
+
+    item.taxable = Confirm::new("Taxable?")
+        .with_default(current.is_none_or(|i| i.taxable))
+        .prompt()?;
+
+    let keep = Confirm::new("Add this item?").with_default(true).prompt()?;
+    if !keep {
+        return Ok(ItemPrompt::Discarded);
     }
+
+    Ok(ItemPrompt::Item(item))
 }
 
 // ==========================================
-// 4. Pay / Unpay Logic (Filters & Rename)
+// 3. PDF Generation (New Logic)
 // ==========================================
 
-fn change_invoice_status(root: &Path, target_paid: bool) {
-    let output_dir = root.join("output");
-    if !output_dir.exists() { println!("❌ No output directory found."); return; }
-    
-    println!("🔍 Scanning invoices...");
-    let mut files = Vec::new();
-    let mut stack = vec![output_dir];
-    while let Some(dir) = stack.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.extension().map_or(false, |e| e == "typ") {
-                    files.push(path);
-                }
+// New invoice (or estimate) from the wizard, or fully from CLI flags
+// With `draft`, the collected input is saved under drafts/ instead of being numbered and rendered
+fn create_document(root: &Path, data_dir: &Path, sender_config: &SenderConfig, invoice_prefix: &str, args: NewArgs, is_estimate: bool, draft: bool) -> anyhow::Result<()> {
+    let NewArgs { copies, client, project, item, tax, terms, discount, notes, date, sender, template, proof } = args;
+    // With --client, --project and --item all given, no prompts are shown
+    let scripted = client.is_some() && project.is_some() && !item.is_empty();
+
+    let sender_config = &match sender {
+        Some(name) => load_sender_profile(root, &name)?,
+        None if scripted => sender_config.clone(),
+        None => select_sender(root, sender_config)?,
+    };
+    let template = template.map(|name| resolve_template(root, &name)).transpose()?;
+
+    let client_id = match client {
+        Some(id) if data_dir.join(&id).join("info.toml").exists() => id,
+        Some(id) => anyhow::bail!("Client '{}' not found", id),
+        None => select_or_create_client(data_dir)?,
+    };
+    say!("✅ Selected Client: {}", client_id);
+
+    let (client_config, selected_project) = match project {
+        Some(project_id) => {
+            let config = load_client_config(data_dir, &client_id)?;
+            match config.projects.iter().find(|p| p.id == project_id).cloned() {
+                Some(p) => (config, p),
+                None => anyhow::bail!("Project '{}' not found for client '{}'", project_id, client_id),
             }
         }
-    }
+        None => select_or_create_project(data_dir, &client_id)?,
+    };
+    say!("✅ Selected Project: {} ({})", selected_project.name.as_deref().unwrap_or("No Name"), selected_project.address.street);
+
+    let items = if item.is_empty() {
+        let mut items = enter_invoice_items(sender_config, &[])?;
+        if !items.is_empty() && !review_items(&mut items, sender_config)? {
+            say!("⏸️  Invoice cancelled.");
+            return Ok(());
+        }
+        items
+    } else {
+        item
+    };
+    
+    if !items.is_empty() {
+        // Date selection
+        let date = match date {
+            Some(d) => d,
+            None if scripted => Local::now().date_naive(),
+            None => DateSelect::new(if is_estimate { "Estimate Date:" } else { "Invoice Date:" })
+                .with_default(Local::now().date_naive())
+                .prompt()?,
+        };
 
-    // Filter logic
-    let filtered_files: Vec<PathBuf> = files.into_iter().filter(|p| {
-        let name = p.file_stem().unwrap().to_string_lossy();
-        if name.ends_with("_VOID") { return false; } // Skip voided invoices
+        // Estimates aren't payable, so they carry no terms or due date
+        let (terms, due_date) = match terms {
+            _ if is_estimate => (String::new(), None),
+            Some(days) => with_due(net_terms(date, days)),
+            None if scripted => with_due(net_terms(date, DEFAULT_TERM_DAYS)),
+            None => with_due(ask_for_terms(date)?),
+        };
 
-        let is_currently_paid = name.ends_with("_PAID");
-        if target_paid {
-            !is_currently_paid // Pay: Select only unpaid
-        } else {
-            is_currently_paid  // Unpay: Select only paid
+        let notes = match notes {
+            Some(n) => Some(n).filter(|n| !n.trim().is_empty()),
+            None if scripted => None,
+            None => ask_for_notes()?,
+        };
+
+        let discount = match discount {
+            Some(d) => Some(d),
+            None if scripted => None,
+            None => ask_for_discount()?,
+        };
+
+        // The project's rate comes first, then the client's; --tax overrides both
+        let default_rate = selected_project.tax_rate.or(client_config.default_tax_rate);
+        let tax = match tax {
+            Some(rate) => TaxChoice::from_percent(rate, sender_config.tax_inclusive),
+            None if client_config.tax_exempt => {
+                say!("ℹ️  {} is tax-exempt, no {} added.", client_config.name, sender_config.tax_label);
+                TaxChoice::from_percent(0.0, false)
+            }
+            None if scripted => TaxChoice::from_percent(default_rate.unwrap_or(0.0), sender_config.tax_inclusive),
+            None => ask_for_tax(default_rate, sender_config)?,
+        };
+        
+        let copy_labels: Vec<String> = copies.iter()
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+            .collect();
+
+        let template = match template {
+            Some(name) => Some(name),
+            None if scripted => None,
+            None => select_template(root)?,
+        };
+
+        let entered_rate = tax.entered_rate;
+        let input = InvoiceInput {
+            items, date, tax_rate: tax.rate, tax_status: tax.status, second_tax: tax.second_tax, tax_inclusive: tax.inclusive,
+            discount, terms, due_date, copy_labels, is_estimate, credit_for: None, notes, template,
+        };
+        if proof
+            && let Some(pdf_path) = write_proof(root, &client_id, &client_config, &selected_project, &input, sender_config)?
+        {
+            open_and_reveal(&pdf_path);
         }
-    }).collect();
+        if draft {
+            save_draft(root, &client_id, &selected_project.id, sender_config, input)?;
+        } else if !proof
+            && generate_pdf(root, &client_id, &client_config, &selected_project, &input, sender_config, invoice_prefix)?
+            && let Some(rate) = entered_rate
+        {
+            remember_tax_rate(rate);
+        }
+    } else {
+        say!("❌ No items entered. Aborting.");
+    }
+    Ok(())
+}
+
+fn generate_pdf(
+    root: &Path, 
+    client_id: &str, 
+    client: &ClientConfig, 
+    project: &Project, 
+    input: &InvoiceInput,
+    sender: &SenderConfig,
+    invoice_prefix: &str,
+) -> anyhow::Result<bool> {
+    let Some(pdf_path) = write_invoice(root, client_id, client, project, input, sender, invoice_prefix)? else { return Ok(false) };
+    copy_path_to_clipboard(&pdf_path);
+    open_and_reveal(&pdf_path);
+    Ok(true)
+}
 
-    if filtered_files.is_empty() {
-        println!("❌ No matching invoices found.");
+// Only with `--copy-path` / `copy_path = true`. Headless sessions have no clipboard;
+// that is only worth a warning.
+fn copy_path_to_clipboard(path: &Path) {
+    if !COPY_PATH.load(std::sync::atomic::Ordering::Relaxed) {
         return;
     }
-    
-    // Sort
-    let mut sorted_files = filtered_files;
-    sorted_files.sort_by_key(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
-    sorted_files.reverse();
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    match set_clipboard(&path.to_string_lossy()) {
+        Ok(()) => say!("📋 Path copied to clipboard."),
+        Err(e) => say!("⚠️  Could not copy to clipboard: {}", e),
+    }
+}
 
-    let options: Vec<String> = sorted_files.iter()
-        .map(|p| p.strip_prefix(root.join("output")).unwrap_or(p).to_string_lossy().to_string())
-        .collect();
+// X11 and Wayland clipboards are served by the process that set them and vanish when
+// it exits, so hand the text to wl-copy / xclip / xsel, which keep running in the background
+#[cfg(target_os = "linux")]
+fn set_clipboard(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let tools: [(&str, &[&str]); 3] = [("wl-copy", &[]), ("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])];
+    for (tool, args) in tools.into_iter().filter(|(tool, _)| wayland || *tool != "wl-copy") {
+        // The tools fork and hold the selection; detach their output so we don't wait on it
+        let Ok(mut child) = Command::new(tool).args(args)
+            .stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null())
+            .spawn() else { continue };
+        child.stdin.take().unwrap().write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{} exited with {}", tool, status)),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    Err(format!("install {} to copy paths", if wayland { "wl-clipboard" } else { "xclip or xsel" }))
+}
 
-    let action_name = if target_paid { "Mark as PAID" } else { "Mark as UNPAID" };
-    
-    let selection = Select::new(&format!("Select Invoice to {}:", action_name), options)
-        .with_page_size(10)
-        .prompt();
+#[cfg(not(target_os = "linux"))]
+fn set_clipboard(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut c| c.set_text(text.to_string()))
+        .map_err(|e| e.to_string())
+}
 
-    match selection {
-        Ok(choice) => {
-            let old_typ_path = root.join("output").join(&choice);
-            let old_pdf_path = old_typ_path.with_extension("pdf");
+// --- Drafts (drafts/<client_id>_<timestamp>.draft.json) ---
+
+// An unnumbered invoice; kept outside output/ so summaries and lists never see it
+#[derive(Serialize, Deserialize)]
+struct InvoiceDraft {
+    client_id: String,
+    project_id: String,
+    #[serde(default)]
+    sender_profile: Option<String>,
+    saved_at: chrono::NaiveDateTime,
+    input: InvoiceInput,
+}
 
-            if let Ok(content) = fs::read_to_string(&old_typ_path) {
-                // Replace is_paid status
-                let from_str = if target_paid { "is_paid: false" } else { "is_paid: true" };
-                let to_str   = if target_paid { "is_paid: true" }  else { "is_paid: false" };
-                
-                let new_content = content.replace(from_str, to_str);
-                
-                // Calculate new filename
-                let parent = old_typ_path.parent().unwrap();
-                let stem = old_typ_path.file_stem().unwrap().to_string_lossy();
-                
-                let new_stem = if target_paid {
-                    format!("{}_PAID", stem) // Add suffix
-                } else {
-                    stem.replace("_PAID", "") // Remove suffix
-                };
+const DRAFT_SUFFIX: &str = ".draft.json";
 
-                let new_typ_path = parent.join(format!("{}.typ", new_stem));
-                let new_pdf_path = parent.join(format!("{}.pdf", new_stem));
+fn save_draft(root: &Path, client_id: &str, project_id: &str, sender: &SenderConfig, input: InvoiceInput) -> anyhow::Result<()> {
+    let saved_at = Local::now().naive_local();
+    let draft = InvoiceDraft {
+        client_id: client_id.to_string(),
+        project_id: project_id.to_string(),
+        sender_profile: sender.profile.clone(),
+        saved_at,
+        input,
+    };
 
-                fs::write(&new_typ_path, new_content).expect("Failed to write updated .typ");
-                
-                // Rename and cleanup
-                if new_typ_path != old_typ_path {
-                    println!("♻️  Renaming to: {}", new_stem);
-                    fs::remove_file(&old_typ_path).ok();
-                    if old_pdf_path.exists() { fs::remove_file(&old_pdf_path).ok(); }
-                }
+    let draft_dir = root.join("drafts");
+    fs::create_dir_all(&draft_dir).with_context(|| format!("Failed to create {:?}", draft_dir))?;
+    let path = draft_dir.join(format!("{}_{}{}", client_id, saved_at.format("%Y%m%d-%H%M%S"), DRAFT_SUFFIX));
+    fs::write(&path, serde_json::to_string_pretty(&draft)?).with_context(|| format!("Failed to write {:?}", path))?;
 
-                println!("🔨 Re-compiling...");
-                match Command::new("typst").arg("compile").arg(&new_typ_path).arg(&new_pdf_path).status() {
-                    Ok(s) if s.success() => {
-                        println!("✅ Done!");
-                        open_and_reveal(&new_pdf_path);
-                    },
-                    _ => println!("❌ Re-compilation failed."),
-                }
-            }
-        },
-        Err(_) => println!("Cancelled"),
-    }
+    say!("📝 Draft saved: {:?}", path);
+    say!("   Run 'finalize' to number and generate it.");
+    Ok(())
 }
 
-fn void_invoice(root: &Path) {
-    let output_dir = root.join("output");
-    if !output_dir.exists() { println!("❌ No output directory found."); return; }
-    
-    println!("🔍 Scanning invoices...");
-    let mut files = Vec::new();
-    let mut stack = vec![output_dir];
-    while let Some(dir) = stack.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.extension().map_or(false, |e| e == "typ") {
-                    files.push(path);
+// Drafts with their file path, oldest first
+fn load_drafts(root: &Path) -> Vec<(PathBuf, InvoiceDraft)> {
+    let mut drafts: Vec<(PathBuf, InvoiceDraft)> = fs::read_dir(root.join("drafts"))
+        .map(|entries| entries.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.to_string_lossy().ends_with(DRAFT_SUFFIX))
+            .filter_map(|p| {
+                let content = fs::read_to_string(&p).ok()?;
+                match serde_json::from_str(&content) {
+                    Ok(draft) => Some((p, draft)),
+                    Err(e) => {
+                        say!("⚠️  Skipping unreadable draft {:?}: {}", p, e);
+                        None
+                    }
                 }
-            }
-        }
-    }
-
-    // Filter out already voided invoices and paid invoices
-    let filtered_files: Vec<PathBuf> = files.into_iter().filter(|p| {
-        let name = p.file_stem().unwrap().to_string_lossy();
-        !name.ends_with("_VOID") && !name.ends_with("_PAID")
-    }).collect();
+            })
+            .collect())
+        .unwrap_or_default();
+    drafts.sort_by_key(|(_, d)| d.saved_at);
+    drafts
+}
 
-    if filtered_files.is_empty() {
-        println!("❌ No matching invoices found.");
-        return;
+// Review the draft's items, date it (today by default) and generate it under the next invoice ID
+fn finalize_draft(root: &Path, data_dir: &Path, sender_config: &SenderConfig, invoice_prefix: &str) -> anyhow::Result<()> {
+    let mut drafts = load_drafts(root);
+    if drafts.is_empty() {
+        say!("❌ No drafts found.");
+        return Ok(());
     }
-    
-    // Sort
-    let mut sorted_files = filtered_files;
-    sorted_files.sort_by_key(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
-    sorted_files.reverse();
 
-    let options: Vec<String> = sorted_files.iter()
-        .map(|p| p.strip_prefix(root.join("output")).unwrap_or(p).to_string_lossy().to_string())
+    let options: Vec<String> = drafts.iter()
+        .map(|(_, d)| {
+            let subtotal: f64 = d.input.items.iter().map(|i| i.amount).sum();
+            format!("{} | {} | {} item(s), {} | saved {}",
+                d.client_id, d.project_id, d.input.items.len(), sender_config.format_money(subtotal), d.saved_at.format("%m/%d/%Y %H:%M"))
+        })
         .collect();
+    let index = Select::new("Select Draft to FINALIZE:", options).raw_prompt()?.index;
+    let (draft_path, mut draft) = drafts.remove(index);
 
-    let selection = Select::new("Select Invoice to VOID:", options)
-        .with_page_size(10)
-        .prompt();
-
-    match selection {
-        Ok(choice) => {
-            let old_typ_path = root.join("output").join(&choice);
-            let old_pdf_path = old_typ_path.with_extension("pdf");
-
-            if let Ok(content) = fs::read_to_string(&old_typ_path) {
-                // Update is_void status
-                // We look for "is_void: false" and replace it with "is_void: true"
-                // If "is_void" is not present (old invoices), we might need to append it, 
-                // but since we updated the template and generate_pdf, new ones have it.
-                // For old ones, we can just replace the end of the file or use regex.
-                // But simpler: just replace "is_void: false" -> "is_void: true"
-                // If it doesn't exist, we append it before the closing parenthesis.
-                
-                let new_content = if content.contains("is_void: false") {
-                    content.replace("is_void: false", "is_void: true")
-                } else {
-                    // Fallback for older files: insert before the last closing parenthesis
-                    // This is a bit risky if the file structure is different, but standard template ends with )
-                    if let Some(last_paren) = content.rfind(')') {
-                        let mut c = content.clone();
-                        c.insert_str(last_paren, ", is_void: true");
-                        c
-                    } else {
-                        content // Should not happen
-                    }
-                };
-                
-                // Calculate new filename
-                let parent = old_typ_path.parent().unwrap();
-                let stem = old_typ_path.file_stem().unwrap().to_string_lossy();
-                let new_stem = format!("{}_VOID", stem);
+    let sender = &match &draft.sender_profile {
+        Some(name) => load_sender_profile(root, name)?,
+        None => sender_config.clone(),
+    };
+    let client = load_client_config(data_dir, &draft.client_id)?;
+    let Some(project) = client.projects.iter().find(|p| p.id == draft.project_id).cloned() else {
+        anyhow::bail!("Project '{}' not found for client '{}'", draft.project_id, draft.client_id);
+    };
 
-                let new_typ_path = parent.join(format!("{}.typ", new_stem));
-                let new_pdf_path = parent.join(format!("{}.pdf", new_stem));
+    if !review_items(&mut draft.input.items, sender)? {
+        say!("⏸️  Draft kept, nothing generated.");
+        return Ok(());
+    }
 
-                fs::write(&new_typ_path, new_content).expect("Failed to write updated .typ");
-                
-                // Rename/Cleanup
-                if new_typ_path != old_typ_path {
-                    println!("♻️  Renaming to: {}", new_stem);
-                    fs::remove_file(&old_typ_path).ok();
-                    if old_pdf_path.exists() { fs::remove_file(&old_pdf_path).ok(); }
-                }
+    // Keep the payment terms: the due date moves with the invoice date
+    let date = DateSelect::new("Invoice Date:").with_default(Local::now().date_naive()).prompt()?;
+    let input = &mut draft.input;
+    input.due_date = input.due_date.map(|due| date + (due - input.date));
+    input.date = date;
 
-                println!("🔨 Re-compiling...");
-                match Command::new("typst").arg("compile").arg(&new_typ_path).arg(&new_pdf_path).status() {
-                    Ok(s) if s.success() => {
-                        println!("✅ Done! Invoice marked as VOID.");
-                        open_and_reveal(&new_pdf_path);
-                    },
-                    _ => println!("❌ Re-compilation failed."),
-                }
-            }
-        },
-        Err(_) => println!("Cancelled"),
+    if let Some(pdf_path) = write_invoice(root, &draft.client_id, &client, &project, &draft.input, sender, invoice_prefix)? {
+        fs::remove_file(&draft_path).ok();
+        copy_path_to_clipboard(&pdf_path);
+        open_and_reveal(&pdf_path);
     }
+    Ok(())
 }
 
-// ==========================================
-// 5. List Logic
-// ==========================================
+// Folder for a new invoice under output/, following output_layout
+fn invoice_dir(root: &Path, date: NaiveDate, client_id: &str) -> PathBuf {
+    let layout = OUTPUT_LAYOUT.get().map(String::as_str).unwrap_or(DEFAULT_OUTPUT_LAYOUT);
+    layout_dir(root, layout, date, client_id)
+}
 
-fn list_invoices_by_status(root: &Path, show_paid: bool) {
-    let output_dir = root.join("output");
-    println!("--- List of {} Invoices ---", if show_paid { "PAID" } else { "UNPAID" });
+fn layout_dir(root: &Path, layout: &str, date: NaiveDate, client_id: &str) -> PathBuf {
+    let relative = layout
+        .replace("{year}", &date.format("%Y").to_string())
+        .replace("{month}", &date.format("%m").to_string())
+        .replace("{client}", client_id);
+    // Only plain folder names, so a layout can't point outside output/
+    relative.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .fold(root.join("output"), |dir, part| dir.join(part))
+}
 
-    let mut stack = vec![output_dir];
-    let mut count = 0;
-    while let Some(dir) = stack.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.extension().map_or(false, |e| e == "pdf") {
-                    let name = path.file_stem().unwrap().to_string_lossy();
-                    if name.ends_with("_VOID") { continue; } // Skip voided
-
-                    let is_paid = name.ends_with("_PAID");
-                    
-                    if is_paid == show_paid {
-                        let relative = path.strip_prefix(root.join("output")).unwrap_or(&path);
-                        println!("📄 {}", relative.to_string_lossy());
-                        count += 1;
-                    }
-                }
-            }
-        }
-    }
-    if count == 0 { println!("(None found)"); }
+// File name (without extension) of a new invoice from filename_pattern, made filesystem-safe
+fn invoice_filename(invoice_id: &str, client_id: &str, project_id: &str, date: NaiveDate) -> String {
+    let pattern = FILENAME_PATTERN.get().map(String::as_str).unwrap_or(DEFAULT_FILENAME_PATTERN);
+    let name = pattern
+        .replace("{id}", invoice_id)
+        .replace("{client}", client_id)
+        .replace("{project}", project_id)
+        .replace("{date}", &date.format("%Y-%m-%d").to_string());
+    filename_safe(&name).trim_matches('.').to_string()
 }
 
-// ==========================================
-// 6. Open Folder Logic
-// ==========================================
+fn filename_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
 
-fn open_folder_wizard(root: &Path) {
-    let output_root = root.join("output");
-    let mut options = Vec::new();
+// Whole-stem regex for names written with `pattern`, capturing the invoice ID at {id}
+fn filename_regex(pattern: &str) -> Regex {
+    let placeholder_re = Regex::new(r"\{(id|client|project|date)\}").unwrap();
+    let mut re = String::from("^");
+    let mut last = 0;
+    for m in placeholder_re.find_iter(pattern) {
+        re.push_str(&regex::escape(&filename_safe(&pattern[last..m.start()])));
+        re.push_str(match m.as_str() {
+            "{id}" => r"([A-Za-z]+\d{8}-\d+)",
+            "{date}" => r"\d{4}-\d{2}-\d{2}",
+            _ => ".+",
+        });
+        last = m.end();
+    }
+    re.push_str(&regex::escape(filename_safe(&pattern[last..]).trim_end_matches('.')));
+    re.push('$');
+    Regex::new(&re).unwrap()
+}
+
+// Client of an invoice: the sidecar's client_id, or the folder name for legacy
+// invoices (always stored as output/<year>/<client_id>/)
+fn invoice_client_id(path: &Path) -> Option<String> {
+    read_sidecar(path).map(|r| r.client_id)
+        .or_else(|| Some(path.parent()?.file_name()?.to_string_lossy().to_string()))
+}
+
+// All invoice .typ files of one client, wherever the layout put them
+fn client_invoices(output_dir: &Path, client_id: &str) -> Vec<PathBuf> {
+    collect_files(output_dir, "typ")
+        .into_iter()
+        .filter(|p| invoice_client_id(p).is_some_and(|id| id == client_id))
+        .collect()
+}
+
+// Allocate the next invoice_id, render and compile; returns the PDF path on success
+// and None when the template or compilation failed (already reported)
+fn write_invoice(
+    root: &Path, 
+    client_id: &str, 
+    client: &ClientConfig, 
+    project: &Project, 
+    input: &InvoiceInput,
+    sender: &SenderConfig,
+    invoice_prefix: &str,
+) -> anyhow::Result<Option<PathBuf>> {
+    let date = input.date;
+    let kind_prefix = if input.credit_for.is_some() {
+        CREDIT_PREFIX
+    } else if input.is_estimate {
+        ESTIMATE_PREFIX
+    } else {
+        invoice_prefix
+    };
+    let mut invoice_id = next_invoice_id(root, date, kind_prefix);
+
+    let output_dir = invoice_dir(root, date, client_id);
+    fs::create_dir_all(&output_dir).with_context(|| format!("Failed to create {:?}", output_dir))?;
+
+    // Filename from filename_pattern, by default HI20251214-01_ProjectID.pdf.
+    // Re-checked before writing: another run or a copied file may have taken the ID since the scan.
+    let (typ_path, pdf_path) = loop {
+        let filename_base = invoice_filename(&invoice_id, client_id, &project.id, date);
+        let typ_path = output_dir.join(format!("{}.typ", filename_base));
+        let pdf_path = output_dir.join(format!("{}.pdf", filename_base));
+        let sidecar = output_dir.join(format!("{}.json", invoice_id));
+        if !invoice_id_in_use(root, &invoice_id, &[&typ_path, &pdf_path, &sidecar]) {
+            break (typ_path, pdf_path);
+        }
+        let next_id = bump_invoice_id(&invoice_id);
+        say!("⚠️  Invoice ID {} is already in use, using {} instead.", invoice_id, next_id);
+        invoice_id = next_id;
+    };
+
+    // Construct Context; the printed date is the chosen invoice date, matching the ID
+    let context_data = build_invoice_context(&invoice_id, date, sender, client, project, input, InvoiceStatus::Unpaid);
+    let mut record = InvoiceRecord::from_context(&context_data, client_id, date, input.due_date, &input.tax_status, input.discount);
+    record.template = input.template.clone();
+    record.second_tax = input.second_tax.clone();
+
+    if render_invoice(root, &context_data, &record, &typ_path, &pdf_path)? {
+        say!("✅ PDF Generated: {:?}", pdf_path);
+        Ok(Some(pdf_path))
+    } else {
+        Ok(None)
+    }
+}
+
+// Stem and printed number of proofs; never matches an invoice ID, so next_invoice_id ignores it
+const PROOF_ID: &str = "DRAFT";
+
+// Watermarked preview in drafts/DRAFT_<client>_<project>.pdf, replaced by the next proof.
+// No invoice number is used up and no sidecar is written.
+fn write_proof(
+    root: &Path,
+    client_id: &str,
+    client: &ClientConfig,
+    project: &Project,
+    input: &InvoiceInput,
+    sender: &SenderConfig,
+) -> anyhow::Result<Option<PathBuf>> {
+    let mut context_data = build_invoice_context(PROOF_ID, input.date, sender, client, project, input, InvoiceStatus::Unpaid);
+    context_data.is_draft = true;
+    let mut record = InvoiceRecord::from_context(&context_data, client_id, input.date, input.due_date, &input.tax_status, input.discount);
+    record.template = input.template.clone();
+
+    let draft_dir = root.join("drafts");
+    fs::create_dir_all(&draft_dir).with_context(|| format!("Failed to create {:?}", draft_dir))?;
+    let filename_base = format!("{}_{}_{}", PROOF_ID, client_id, project.id);
+    let typ_path = draft_dir.join(format!("{}.typ", filename_base));
+    let pdf_path = draft_dir.join(format!("{}.pdf", filename_base));
+
+    if render_invoice(root, &context_data, &record, &typ_path, &pdf_path)? {
+        say!("✅ Draft proof generated: {:?}", pdf_path);
+        say!("   No invoice number was used; run 'new' without --proof to issue it.");
+        Ok(Some(pdf_path))
+    } else {
+        Ok(None)
+    }
+}
+
+// --- Invoice ID Generation (INV20251214-01, QU20251214-01 / CR20251214-01 for estimates / credit notes) ---
+fn next_invoice_id(root: &Path, date: NaiveDate, kind_prefix: &str) -> String {
+    let date_str = date.format("%Y%m%d").to_string(); // 20251214
+    let prefix = format!("{}{}", kind_prefix, date_str); // HI20251214
     
-    let root_opt = "📂 Open Root Output Directory".to_string();
-    options.push(root_opt.clone());
+    // Scan the whole output directory: the layout decides where the year's invoices live
+    let output_root = root.join("output");
+    let mut next_idx = 1;
+    // The ID can sit anywhere in the filename (see filename_pattern)
+    let mut bump = |id: &str| {
+        if let Some(rest) = id.strip_prefix(&prefix)
+            && let Some(num_part) = rest.strip_prefix('-')
+            && let Ok(idx) = num_part.parse::<u32>()
+            && idx >= next_idx
+        {
+            next_idx = idx + 1;
+        }
+    };
+
+    // Archived invoices keep their numbers too
+    for entry in archived_invoices(&output_root) {
+        bump(&entry.info.id);
+    }
 
     if output_root.exists() {
-        if let Ok(years) = fs::read_dir(&output_root) {
-            for year_entry in years.flatten() {
-                if year_entry.path().is_dir() {
-                    let year_name = year_entry.file_name().to_string_lossy().to_string();
-                    if let Ok(clients) = fs::read_dir(year_entry.path()) {
-                        for client_entry in clients.flatten() {
-                            if client_entry.path().is_dir() {
-                                let client_name = client_entry.file_name().to_string_lossy().to_string();
-                                options.push(format!("{} / {}", year_name, client_name));
-                            }
-                        }
+        let mut stack = vec![output_root];
+        while let Some(dir) = stack.pop() {
+             if let Ok(entries) = fs::read_dir(dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        stack.push(path);
+                    } else if let Some(id) = invoice_id_from_path(&path) {
+                        bump(&id);
                     }
                 }
-            }
+             }
         }
     }
 
-    let mut client_paths: Vec<String> = options.drain(1..).collect();
-    client_paths.sort();
-    client_paths.reverse();
-    
-    let mut final_options = vec![root_opt.clone()];
-    final_options.extend(client_paths);
+    format!("{}-{:02}", prefix, next_idx) // e.g., HI20251214-01
+}
 
-    match Select::new("Select Folder to Open:", final_options).prompt() {
-        Ok(choice) => {
-            let target_path = if choice == root_opt {
-                output_root
-            } else {
-                let parts: Vec<&str> = choice.split(" / ").collect();
-                if parts.len() == 2 {
-                    output_root.join(parts[0]).join(parts[1])
-                } else {
-                    output_root
-                }
+// True when one of `paths` exists or any invoice under output/ already carries `invoice_id`
+fn invoice_id_in_use(root: &Path, invoice_id: &str, paths: &[&Path]) -> bool {
+    if paths.iter().any(|p| p.exists()) {
+        return true;
+    }
+    let output_root = root.join("output");
+    collect_files(&output_root, "typ")
+        .into_iter()
+        .chain(collect_files(&output_root, "pdf"))
+        .any(|path| invoice_id_from_path(&path).is_some_and(|id| id == invoice_id))
+}
+
+// HI20251214-01 -> HI20251214-02
+fn bump_invoice_id(invoice_id: &str) -> String {
+    match invoice_id.rsplit_once('-') {
+        Some((prefix, num)) => format!("{}-{:02}", prefix, num.parse::<u32>().unwrap_or(0) + 1),
+        None => format!("{}-02", invoice_id),
+    }
+}
+
+fn build_invoice_context(
+    invoice_id: &str,
+    printed_date: NaiveDate,
+    sender: &SenderConfig,
+    client: &ClientConfig,
+    project: &Project,
+    input: &InvoiceInput,
+    status: InvoiceStatus,
+) -> InvoiceContext {
+    let items = &input.items;
+    let tax_rate = input.tax_rate;
+
+    // Calculate totals (tax applies to taxable lines only)
+    let total_before_tax: f64 = items.iter().map(|i| i.amount).sum();
+    let taxable_subtotal: f64 = items.iter().filter(|i| i.taxable).map(|i| i.amount).sum();
+
+    // Discount reduces the subtotal before tax; the taxable share shrinks proportionally
+    let discount_amount = input.discount.map(|d| d.amount(total_before_tax)).unwrap_or(0.0);
+    let discount_ratio = if total_before_tax > 0.0 { 1.0 - discount_amount / total_before_tax } else { 1.0 };
+
+    // Each tax applies to the same discounted taxable amount. Tax-inclusive prices
+    // contain every tax already, so the net base is backed out first.
+    let mut rates = vec![(sender.tax_label.clone(), tax_rate)];
+    if let Some(second) = &input.second_tax {
+        rates.push((second.label.clone(), second.rate));
+    }
+    let combined_rate: f64 = rates.iter().map(|(_, r)| r).sum();
+    let net = |amount: f64| if input.tax_inclusive {
+        amount * discount_ratio / (1.0 + combined_rate)
+    } else {
+        amount * discount_ratio
+    };
+    // Tax is rounded to cents before it is printed, so the printed lines add up to the total
+    let taxes: Vec<TaxLine> = if tax_rate > 0.0 {
+        rates.into_iter().map(|(label, rate)| {
+            let amount = match sender.rounding_mode {
+                RoundingMode::Total => round_cents(net(taxable_subtotal) * rate),
+                RoundingMode::PerLine => round_cents(items.iter()
+                    .filter(|i| i.taxable)
+                    .map(|i| round_cents(net(i.amount) * rate))
+                    .sum()),
             };
-            println!("🚀 Opening: {:?}", target_path);
-            
-            #[cfg(target_os = "macos")]
-            Command::new("open").arg(&target_path).spawn().ok();
-            #[cfg(target_os = "windows")]
-            Command::new("explorer").arg(&target_path).spawn().ok();
-        },
-        Err(_) => println!("Operation cancelled."),
+            TaxLine { label, rate, amount }
+        }).collect()
+    } else {
+        vec![]
+    };
+    let tax_amount: f64 = taxes.iter().map(|t| t.amount).sum();
+    let total = round_cents(if input.tax_inclusive {
+        total_before_tax - discount_amount
+    } else {
+        total_before_tax - discount_amount + tax_amount
+    });
+
+    let tax_display_str = if let Some(first) = taxes.first() {
+        sender.format_money(first.amount) // Show amount if tax exists
+    } else {
+        input.tax_status.clone() // Show "Exempt" or "Included" if no tax
+    };
+
+    // PDF metadata so document-management tools can index the invoice
+    let client_display = client.name.replace("Attn:", "").trim().to_string();
+    let mut doc_keywords = vec![invoice_id.to_string(), client_display.clone()];
+    if let Some(project_name) = &project.name {
+        doc_keywords.push(project_name.clone());
+    }
+    doc_keywords.push(sender.format_money(total));
+
+    // A missing logo would fail the Typst compile with a cryptic error
+    let logo_path = sender.logo_path.as_ref().and_then(|logo| {
+        let path = Path::new(logo);
+        if path.is_file() {
+            Some(logo.clone())
+        } else {
+            say!("⚠️  Logo not found: {}, rendering without it.", logo);
+            None
+        }
+    });
+
+    InvoiceContext {
+        id: invoice_id.to_string(),
+        date: sender.format_date(printed_date),
+        sender: sender.clone(),
+        client: client.clone(),
+        project: project.clone(),
+        items: items.to_vec(),
+        subtotal: total_before_tax,
+        tax_amount,
+        total,
+        tax_rate,
+        taxable_subtotal,
+        discount_amount,
+        discount_label: input.discount.map(|d| d.label()).unwrap_or_default(),
+        terms: input.terms.clone(),
+        due_date: input.due_date.map(|d| sender.format_date(d)).unwrap_or_default(),
+        is_void: status.is_void(),
+        is_draft: false,
+        is_paid: status.is_paid(),
+        is_estimate: input.is_estimate,
+        credit_for: input.credit_for.clone(),
+        notes: input.notes.clone(),
+        terms_and_conditions: SenderConfig::footer_text(sender.terms.as_deref()),
+        footer: SenderConfig::footer_text(sender.footer.as_deref()),
+        logo_path,
+        tax_display: tax_display_str,
+        tax_label: sender.tax_label.clone(),
+        taxes,
+        tax_inclusive: input.tax_inclusive,
+        total_words: sender.amount_in_words(total),
+        bank_details: sender.bank_details(),
+        paper: sender.paper().unwrap_or("us-letter").to_string(),
+        accent_color: sender.accent_color.clone(),
+        currency_symbol: sender.symbol(),
+        copy_labels: input.copy_labels.clone(),
+        doc_title: format!("{} {} - {}", document_kind(input), invoice_id, client_display),
+        doc_author: sender.name.clone(),
+        doc_keywords,
     }
 }
 
-// ==========================================
-// Search Logic
-// ==========================================
+fn document_kind(input: &InvoiceInput) -> &'static str {
+    if input.credit_for.is_some() {
+        "Credit Note"
+    } else if input.is_estimate {
+        "Estimate"
+    } else {
+        "Invoice"
+    }
+}
 
-fn search_invoices(root: &Path) {
-    let output_root = root.join("output");
-    if !output_root.exists() {
-        println!("❌ No output directory found.");
-        return;
+// Tera filter `{{ amount | money }}`: the sender's currency formatting, e.g. "$12,345.67"
+fn money_filter(sender: SenderConfig) -> impl tera::Filter {
+    move |value: &tera::Value, _: &std::collections::HashMap<String, tera::Value>| {
+        let amount = value.as_f64().ok_or_else(|| tera::Error::msg("money filter expects a number"))?;
+        Ok(tera::Value::String(sender.format_money(amount)))
     }
+}
 
-    println!("🔍 Loading invoices...");
+// Inside a Typst string literal: only `\` and `"` need escaping.
+// The app's `\n` line-break markup survives as a literal `\n` for parse_desc.
+fn typst_escape_str(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
 
-    // Gather all .typ files
-    let mut typ_files = Vec::new();
-    let mut stack = vec![output_root.clone()];
-    while let Some(dir) = stack.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.extension().map_or(false, |e| e == "typ") {
-                    typ_files.push(path);
-                }
-            }
+// Inside Typst markup (`[...]`): escape every character that starts syntax
+fn typst_escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\#*_`$[]<>@=-+/~\"".contains(c) {
+            escaped.push('\\');
         }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Tera filter wrapping one of the escape functions; numbers and booleans pass through as text
+fn escape_filter(escape: fn(&str) -> String) -> impl tera::Filter {
+    move |value: &tera::Value, _: &std::collections::HashMap<String, tera::Value>| {
+        let text = match value {
+            tera::Value::String(s) => escape(s),
+            tera::Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        Ok(tera::Value::String(text))
     }
+}
 
-    if typ_files.is_empty() {
-        println!("No invoices found.");
-        return;
+// All templates in `template_dir` with the app's filters:
+// `money`, `typst_str` (inside "...") and `typst_markup` (inside [...])
+fn load_templates(template_dir: &Path, sender: &SenderConfig) -> tera::Result<Tera> {
+    let mut tera = Tera::new(template_dir.join("*.tera").to_str().unwrap())?;
+    tera.register_filter("money", money_filter(sender.clone()));
+    tera.register_filter("typst_str", escape_filter(typst_escape_str));
+    tera.register_filter("typst_markup", escape_filter(typst_escape_markup));
+    Ok(tera)
+}
+
+// Render the Tera template into `typ_path` (plus its JSON sidecar) and compile it to `pdf_path` with Typst.
+// False when the template or compilation failed (already reported); Err when a file can't be written.
+fn render_invoice(root: &Path, context_data: &InvoiceContext, record: &InvoiceRecord, typ_path: &Path, pdf_path: &Path) -> anyhow::Result<bool> {
+    if !render_typ(root, context_data, record, typ_path)? {
+        return Ok(false);
     }
 
-    // Build searchable entries: (display_string, path)
-    // Display string includes all searchable info so Select's built-in filter works
-    let client_re = Regex::new(r#"client:\s*\(\s*name:\s*"([^"]+)""#).unwrap();
-    let project_re = Regex::new(r#"project:\s*\([^)]*address:\s*\[([^\]]+)\]"#).unwrap();
-    let desc_re = Regex::new(r#"desc:\s*"([^"]+)""#).unwrap();
-    let amount_re = Regex::new(r"amount:\s*([\d\.]+)").unwrap();
+    // The .typ is still written without Typst: status changes and Edit read it back
+    if !typst_installed() {
+        say!("\n⚠️  'typst' is not installed, using the built-in PDF layout (brew install typst for the full template).");
+        return Ok(render_builtin_pdf(context_data, pdf_path));
+    }
 
-    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    say!("\n🔨 Compiling PDF...");
+    match compile_typst(root, typ_path, pdf_path) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            print_typst_error(&e);
+            Ok(false)
+        }
+    }
+}
 
-    for path in typ_files {
-        let rel_path = path.strip_prefix(&output_root).unwrap_or(&path).to_string_lossy().to_string();
-        
-        let mut search_parts = vec![rel_path.clone()];
+// Write the rendered .typ and the JSON sidecar (none for proofs); false on a template error
+fn render_typ(root: &Path, context_data: &InvoiceContext, record: &InvoiceRecord, typ_path: &Path) -> anyhow::Result<bool> {
+    let template_dir = init_templates(root)?;
 
-        if let Ok(content) = fs::read_to_string(&path) {
-            // Extract client name
-            if let Some(cap) = client_re.captures(&content) {
-                search_parts.push(cap[1].to_string());
-            }
-            // Extract project address
-            if let Some(cap) = project_re.captures(&content) {
-                search_parts.push(cap[1].replace("\\", " ").replace("  ", " "));
-            }
-            // Extract all descriptions
-            for cap in desc_re.captures_iter(&content) {
-                search_parts.push(cap[1].to_string());
-            }
-            // Extract all amounts
-            for cap in amount_re.captures_iter(&content) {
-                search_parts.push(format!("${}", &cap[1]));
-            }
+    let tera = match load_templates(&template_dir, &context_data.sender) {
+        Ok(t) => t,
+        Err(e) => { say!("❌ Template Error: {}", e); return Ok(false); }
+    };
+
+    // The template recorded for this invoice, if it still exists
+    let template_name = match record.template.as_deref() {
+        Some(name) if template_dir.join(name).exists() => name,
+        Some(name) => {
+            say!("⚠️  Template {} not found, using {}.", name, DEFAULT_TEMPLATE_NAME);
+            DEFAULT_TEMPLATE_NAME
         }
+        None => DEFAULT_TEMPLATE_NAME,
+    };
 
-        // Build display: "path | client | project | desc... | $amt"
-        let display = search_parts.join(" | ");
-        entries.push((display, path));
+    let mut context = Context::from_serialize(context_data).context("Failed to build the template context")?;
+    context.insert("qr_path", &write_payment_qr(root, &context_data.sender, typ_path));
+    context.insert("logo_path", &context_data.logo_path.as_ref().and_then(|logo| logo_asset_path(root, Path::new(logo))));
+    let rendered = match tera.render(template_name, &context) {
+        Ok(r) => r,
+        Err(e) => { say!("❌ Template Error: {:?}", e); return Ok(false); }
+    };
+
+    fs::write(typ_path, rendered).with_context(|| format!("Failed to write {:?}", typ_path))?;
+    // Proofs live outside output/ and are never read back
+    if !context_data.is_draft {
+        // No .typ without its sidecar: status changes and summaries would misread it
+        if let Err(e) = write_sidecar(typ_path, record) {
+            fs::remove_file(typ_path).ok();
+            return Err(e);
+        }
+        update_index(&root.join("output"), typ_path);
     }
+    Ok(true)
+}
 
-    // Sort by modified time desc
-    entries.sort_by_key(|(_, p)| std::fs::metadata(p).and_then(|m| m.modified()).ok());
-    entries.reverse();
+// Create templates/ with the default invoice.tera on first use; returns the directory
+fn init_templates(root: &Path) -> anyhow::Result<PathBuf> {
+    let template_dir = root.join("templates");
+    fs::create_dir_all(&template_dir).with_context(|| format!("Failed to create {:?}", template_dir))?;
+    let template_path = template_dir.join(DEFAULT_TEMPLATE_NAME);
+    if !template_path.exists() { 
+        say!("✨ Initializing default template...");
+        fs::write(&template_path, DEFAULT_TEMPLATE).with_context(|| format!("Failed to write {:?}", template_path))?;
+    }
+    let statement_path = template_dir.join(STATEMENT_TEMPLATE_NAME);
+    if !statement_path.exists() {
+        fs::write(&statement_path, DEFAULT_STATEMENT_TEMPLATE).with_context(|| format!("Failed to write {:?}", statement_path))?;
+    }
+    Ok(template_dir)
+}
 
-    let options: Vec<String> = entries.iter().map(|(d, _)| d.clone()).collect();
+// Invoice template file names under templates/ (statement.tera excluded), sorted
+fn invoice_templates(root: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(init_templates(root)?)
+        .map(|entries| entries.flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".tera") && name != STATEMENT_TEMPLATE_NAME)
+            .collect())
+        .unwrap_or_default();
+    names.sort();
+    Ok(names)
+}
 
-    // Use Select with real-time type-to-filter
-    let selection = Select::new("Search Invoice (type to filter):", options.clone())
-        .with_page_size(15)
-        .prompt();
+// Pick a template when there are several; None keeps the default invoice.tera
+fn select_template(root: &Path) -> anyhow::Result<Option<String>> {
+    let templates = invoice_templates(root)?;
+    if templates.len() < 2 {
+        return Ok(None);
+    }
+    let default_index = templates.iter().position(|t| t == DEFAULT_TEMPLATE_NAME).unwrap_or(0);
+    let name = Select::new("Template:", templates).with_starting_cursor(default_index).prompt()?;
+    Ok(Some(name).filter(|n| n != DEFAULT_TEMPLATE_NAME))
+}
 
-    match selection {
-        Ok(choice) => {
-            if let Some(pos) = options.iter().position(|o| o == &choice) {
-                let path = &entries[pos].1;
-                let pdf_path = path.with_extension("pdf");
-                if pdf_path.exists() {
-                    open_and_reveal(&pdf_path);
-                } else {
-                    open_and_reveal(path);
+// --template accepts "simple" or "simple.tera"
+fn resolve_template(root: &Path, name: &str) -> anyhow::Result<String> {
+    let file = if name.ends_with(".tera") { name.to_string() } else { format!("{}.tera", name) };
+    let templates = invoice_templates(root)?;
+    if !templates.contains(&file) {
+        anyhow::bail!("Template '{}' not found (available: {})", name, templates.join(", "));
+    }
+    Ok(file)
+}
+
+// Payment QR as <invoice_id>_qr.png next to the .typ; returns its Typst path, None without payment_uri
+fn write_payment_qr(root: &Path, sender: &SenderConfig, typ_path: &Path) -> Option<String> {
+    let uri = sender.payment_uri.as_deref().filter(|u| !u.trim().is_empty())?;
+    let qr_path = payment_qr_path(typ_path)?;
+    let code = match QrCode::new(uri.trim().as_bytes()) {
+        Ok(code) => code,
+        Err(e) => {
+            say!("⚠️  Could not encode payment_uri as a QR code: {}", e);
+            return None;
+        }
+    };
+    let image = code.render::<Luma<u8>>().min_dimensions(300, 300).build();
+    if let Err(e) = image.save(&qr_path) {
+        say!("⚠️  Could not write {:?}: {}", qr_path, e);
+        return None;
+    }
+    typst_asset_path(root, &qr_path)
+}
+
+fn payment_qr_path(path: &Path) -> Option<PathBuf> {
+    let id = invoice_id_from_path(path)?;
+    Some(path.parent()?.join(format!("{}_qr.png", id)))
+}
+
+fn typst_installed() -> bool {
+    typst_command().arg("--version").output().is_ok()
+}
+
+fn render_builtin_pdf(context_data: &InvoiceContext, pdf_path: &Path) -> bool {
+    match simple_pdf::render(context_data, pdf_path) {
+        Ok(()) => true,
+        Err(e) => {
+            say!("❌ PDF rendering failed: {}", e);
+            false
+        }
+    }
+}
+
+// Recompile all invoices with one Typst process per CPU. With `render`, each .typ is
+// first regenerated from its sidecar so template changes reach old invoices.
+fn recompile_all(root: &Path, sender: &SenderConfig, render: bool) {
+    if !typst_installed() {
+        say!("❌ 'typst' is not installed (brew install typst).");
+        return;
+    }
+    let output_dir = root.join("output");
+    let files = collect_files(&output_dir, "typ");
+    if files.is_empty() {
+        say!("❌ No invoices found.");
+        return;
+    }
+
+    if render {
+        say!("📝 Re-rendering {} invoice(s) with the current template...", files.len());
+        for typ_path in &files {
+            // Legacy invoices without a sidecar keep their .typ as is
+            let Some(record) = read_sidecar(typ_path) else { continue };
+            let sender = &invoice_sender(root, typ_path, sender);
+            match load_invoice_source(root, typ_path, &sender.date_format) {
+                Ok(source) => {
+                    let context_data = build_invoice_context(
+                        &source.invoice_id, source.printed_date, sender, &source.client, &source.project, &source.input, record.status(),
+                    );
+                    if let Err(e) = render_typ(root, &context_data, &record, typ_path) {
+                        say!("⚠️  {}: {:#}", index_key(&output_dir, typ_path), e);
+                    }
                 }
+                Err(e) => say!("⚠️  {}: {}", index_key(&output_dir, typ_path), e),
             }
-        },
-        Err(_) => println!("Cancelled"),
+        }
     }
-}
 
-// ==========================================
-// 7. Config & Utilities
-// ==========================================
+    let workers = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(files.len());
+    say!("🔨 Compiling {} invoice(s) on {} thread(s)...", files.len(), workers);
+
+    // Workers pull the next file index until the list is exhausted
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let failed = std::sync::Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                let Some(typ_path) = files.get(i) else { break };
+                if let Err(e) = compile_typst(root, typ_path, &typ_path.with_extension("pdf")) {
+                    failed.lock().unwrap().push((index_key(&output_dir, typ_path), e));
+                }
+            });
+        }
+    });
 
-fn get_config_path() -> PathBuf {
-    if let Some(proj_dirs) = ProjectDirs::from("com", "invoice-maker", "app") {
-        let config_dir = proj_dirs.config_dir();
-        if !config_dir.exists() { fs::create_dir_all(config_dir).ok(); }
-        return config_dir.join("settings.toml");
+    let mut failed = failed.into_inner().unwrap();
+    failed.sort();
+    say!("\n✅ {} compiled, {} failed.", files.len() - failed.len(), failed.len());
+    for (path, error) in &failed {
+        say!("\n❌ {}", path);
+        for line in error.lines() {
+            say!("   {}", line);
+        }
     }
-    PathBuf::from("settings.toml")
+    // Older .typ files point at assets by absolute path, outside Typst's root
+    if !failed.is_empty() && !render {
+        say!("\n💡 Invoices rendered by an older version may need --render to be compiled again.");
+    }
+}
+
+// The invoice call at the end of a .typ (the injected data) sets both status fields
+fn has_status_fields(content: &str) -> bool {
+    let call = injected_call(content);
+    call.contains("is_paid:") && call.contains("is_void:")
+}
+
+// Flip the status fields of the injected invoice call, leaving the rest of the .typ as printed
+fn set_status_fields(content: &str, is_paid: bool, is_void: bool) -> String {
+    let call = injected_call(content);
+    let head = &content[..content.len() - call.len()];
+    let call = Regex::new(r"is_paid:\s*(true|false)").unwrap().replace(call, format!("is_paid: {}", is_paid));
+    let call = Regex::new(r"is_void:\s*(true|false)").unwrap().replace(&call, format!("is_void: {}", is_void));
+    format!("{}{}", head, call)
 }
 
-fn load_settings() -> Option<AppSettings> {
-    let path = get_config_path();
-    if !path.exists() { return None; }
-    let content = fs::read_to_string(path).ok()?;
-    toml::from_str(&content).ok()
+// Old .typ files may predate the is_paid / is_void fields and the JSON sidecar; status
+// changes then depend on the filename alone. Regenerate those from the data printed on
+// them and their filename status, writing the sidecar, so every invoice goes through
+// the same path. Files that already have the fields only get the sidecar.
+fn migrate_invoices(root: &Path, sender: &SenderConfig) {
+    let output_dir = root.join("output");
+    let files = collect_files(&output_dir, "typ");
+    if files.is_empty() {
+        say!("❌ No invoices found.");
+        return;
+    }
+
+    let (mut upgraded, mut failed) = (0, 0);
+    for typ_path in &files {
+        let content = fs::read_to_string(typ_path).unwrap_or_default();
+        if has_status_fields(&content) && read_sidecar(typ_path).is_some() {
+            continue;
+        }
+        let label = index_key(&output_dir, typ_path);
+        let sender = &invoice_sender(root, typ_path, sender);
+        // Only data printed on the .typ is used, never the current client config.
+        // Files with status fields keep their content; only the flags follow the filename.
+        let done = invoice_record(typ_path, &content, sender).map_err(anyhow::Error::msg).and_then(|record| {
+            if has_status_fields(&content) {
+                return write_printed_invoice(root, typ_path, &content, &record, sender);
+            }
+            let context_data = printed_context(typ_path, &content, &record, invoice_status(typ_path), sender).map_err(anyhow::Error::msg)?;
+            render_invoice(root, &context_data, &record, typ_path, &typ_path.with_extension("pdf"))
+        });
+        match done {
+            Ok(true) => {
+                say!("♻️  Upgraded {}", label);
+                upgraded += 1;
+            }
+            Ok(false) => failed += 1,
+            Err(e) => {
+                say!("⚠️  {}: {:#}", label, e);
+                failed += 1;
+            }
+        }
+    }
+
+    say!("\n✅ {} invoice(s) upgraded, {} already current, {} failed.", upgraded, files.len() - upgraded - failed, failed);
+}
+
+// Compile with the data root as Typst's project root: templates can only read files
+// inside it (see typst_asset_path). On failure, returns Typst's own error output (stderr).
+fn compile_typst(root: &Path, typ_path: &Path, pdf_path: &Path) -> Result<(), String> {
+    let typ_abs = fs::canonicalize(typ_path).unwrap_or_else(|_| typ_path.to_path_buf());
+    let root_abs = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let output = typst_command().arg("compile").arg(&typ_abs).arg(pdf_path).arg("--root").arg(&root_abs)
+        .output()
+        .map_err(|e| format!("Failed to run typst: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() { format!("typst exited with {}", output.status) } else { stderr })
+    }
+}
+
+fn print_typst_error(error: &str) {
+    say!("❌ Compilation failed:");
+    for line in error.lines() {
+        say!("   {}", line);
+    }
+}
+
+// Path of a file inside the data root as Typst sees it with `--root` there: "/assets/logo.png".
+// None for files outside the root, which Typst cannot read.
+fn typst_asset_path(root: &Path, path: &Path) -> Option<String> {
+    let root = fs::canonicalize(root).ok()?;
+    let path = fs::canonicalize(path).ok()?;
+    let parts: Vec<String> = path.strip_prefix(&root).ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(format!("/{}", parts.join("/")))
+}
+
+// A logo outside the data root is copied to <root>/assets/ so Typst can read it
+fn logo_asset_path(root: &Path, logo: &Path) -> Option<String> {
+    if let Some(path) = typst_asset_path(root, logo) {
+        return Some(path);
+    }
+    let assets = root.join("assets");
+    let copy = assets.join(logo.file_name()?);
+    if let Err(e) = fs::create_dir_all(&assets).and_then(|_| fs::copy(logo, &copy)) {
+        say!("⚠️  Could not copy the logo into {:?}: {}, rendering without it.", assets, e);
+        return None;
+    }
+    typst_asset_path(root, &copy)
+}
+
+// --- JSON Sidecar (<invoice_id>.json) ---
+
+// Invoice ID from a filename like HI20251214-01_main-st_PAID.typ or acme_HI20251214-01.pdf:
+// the prefix letters, the date and the sequence number at the {id} position of
+// filename_pattern. Files named before the pattern was changed use the default one.
+fn invoice_id_from_path(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy().to_string();
+    let stem = InvoiceStatus::base_stem(&stem);
+    let pattern = FILENAME_PATTERN.get().map(String::as_str).unwrap_or(DEFAULT_FILENAME_PATTERN);
+    [pattern, DEFAULT_FILENAME_PATTERN].iter()
+        .find_map(|p| filename_regex(p).captures(stem))
+        .map(|caps| caps[1].to_string())
+}
+
+fn sidecar_path(path: &Path) -> Option<PathBuf> {
+    let id = invoice_id_from_path(path)?;
+    Some(path.parent()?.join(format!("{}.json", id)))
+}
+
+fn read_sidecar(path: &Path) -> Option<InvoiceRecord> {
+    let content = fs::read_to_string(sidecar_path(path)?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_sidecar(path: &Path, record: &InvoiceRecord) -> anyhow::Result<()> {
+    if let Some(sidecar) = sidecar_path(path) {
+        let json = serde_json::to_string_pretty(record)?;
+        fs::write(&sidecar, json).with_context(|| format!("Failed to write {:?}", sidecar))?;
+    }
+    Ok(())
+}
+
+// ==========================================
+// 4. Pay / Unpay / Void Logic (Filters & Rename)
+// ==========================================
+
+// Recursively collect files with the given extension
+fn collect_files(dir: &Path, ext: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    stack.push(path);
+                } else if path.extension().is_some_and(|e| e == ext) {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files
+}
+
+// The sidecar is authoritative; the filename suffix covers legacy invoices
+fn invoice_status(path: &Path) -> InvoiceStatus {
+    match read_sidecar(path) {
+        Some(record) => record.status(),
+        None => InvoiceStatus::from_stem(&path.file_stem().unwrap().to_string_lossy()),
+    }
+}
+
+// Estimates (QU prefix) are not revenue and stay out of reports
+fn is_estimate(path: &Path) -> bool {
+    match read_sidecar(path) {
+        Some(record) => record.is_estimate,
+        None => path.file_name().is_some_and(|n| n.to_string_lossy().starts_with(ESTIMATE_PREFIX)),
+    }
+}
+
+// Let the user pick an invoice (.typ) whose current status is one of `from`
+fn select_invoice(root: &Path, from: &[InvoiceStatus], prompt: &str) -> Option<PathBuf> {
+    let output_dir = root.join("output");
+    if !output_dir.exists() { say!("❌ No output directory found."); return None; }
+
+    say!("🔍 Scanning invoices...");
+    let mut files: Vec<PathBuf> = collect_files(&output_dir, "typ")
+        .into_iter()
+        .filter(|p| from.contains(&invoice_status(p)))
+        .collect();
+
+    if files.is_empty() {
+        say!("❌ No matching invoices found.");
+        return None;
+    }
+
+    // Sort (newest first)
+    files.sort_by_key(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+    files.reverse();
+
+    let options: Vec<String> = files.iter()
+        .map(|p| p.strip_prefix(&output_dir).unwrap_or(p).to_string_lossy().to_string())
+        .collect();
+
+    match Select::new(prompt, options).with_page_size(10).prompt() {
+        Ok(choice) => Some(output_dir.join(choice)),
+        Err(_) => { say!("Cancelled"); None }
+    }
+}
+
+// Sidecar record for a legacy invoice, built from its parsed .typ and filename status
+fn legacy_record(typ_path: &Path, source: &InvoiceSource, sender: &SenderConfig) -> InvoiceRecord {
+    let stem = typ_path.file_stem().unwrap().to_string_lossy().to_string();
+    let context_data = build_invoice_context(
+        &source.invoice_id, source.printed_date, sender, &source.client, &source.project, &source.input, invoice_status(typ_path),
+    );
+    let mut record = InvoiceRecord::from_context(
+        &context_data, &source.client_id, source.input.date, source.input.due_date, &source.input.tax_status, source.input.discount,
+    );
+    record.is_paid = InvoiceStatus::before_void(&stem).is_paid();
+    record
+}
+
+// The sidecar record of an invoice; legacy invoices get one built from their .typ alone
+fn invoice_record(typ_path: &Path, content: &str, sender: &SenderConfig) -> Result<InvoiceRecord, String> {
+    if let Some(record) = read_sidecar(typ_path) {
+        return Ok(record);
+    }
+    let source = parse_typ_source(typ_path, content, &sender.date_format)?;
+    let mut sender = sender.clone();
+    apply_typ_sender(&mut sender, content);
+    Ok(legacy_record(typ_path, &source, &sender))
+}
+
+// Record that an unpaid invoice went out, for users who email it themselves.
+// Only the sidecar changes; the PDF is left as is.
+fn mark_invoice_sent(root: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let Some(typ_path) = select_invoice(root, &[InvoiceStatus::Unpaid], "Select Invoice to mark as SENT:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    let content = fs::read_to_string(&typ_path).with_context(|| format!("Failed to read {:?}", typ_path))?;
+    let mut record = invoice_record(&typ_path, &content, sender).map_err(anyhow::Error::msg)?;
+
+    if let Some(sent_at) = record.sent_at {
+        let again = Confirm::new(&format!("Already marked sent on {}. Update to now?", sent_at.format("%m/%d/%Y %H:%M")))
+            .with_default(false)
+            .prompt()?;
+        if !again {
+            say!("⏸️  Operation cancelled.");
+            return Ok(());
+        }
+    }
+
+    let sent_at = Local::now().naive_local();
+    record.sent_at = Some(sent_at);
+    write_sidecar(&typ_path, &record)?;
+    say!("✅ {} marked as SENT ({}).", record.id, sent_at.format("%m/%d/%Y %H:%M"));
+    Ok(())
+}
+
+// Move an invoice to `status`. The status lives in the JSON sidecar; the .typ keeps
+// everything as printed and only has its is_paid / is_void fields flipped before it
+// is recompiled, so client, project and sender changes since never leak into old
+// invoices. Legacy invoices (no sidecar, `_PAID` / `_VOID` filename suffix) get a
+// sidecar and lose the suffix on their first status change.
+// Returns the PDF path when compilation succeeded.
+fn set_invoice_status(root: &Path, typ_path: &Path, status: InvoiceStatus, sender: &SenderConfig) -> anyhow::Result<Option<PathBuf>> {
+    let sender = &invoice_sender(root, typ_path, sender);
+    let content = fs::read_to_string(typ_path).with_context(|| format!("Failed to read {:?}", typ_path))?;
+    if !has_status_fields(&content) {
+        anyhow::bail!("This invoice predates status fields; run `invoice-maker migrate` first.");
+    }
+
+    let stem = typ_path.file_stem().unwrap().to_string_lossy().to_string();
+    let mut record = invoice_record(typ_path, &content, sender).map_err(anyhow::Error::msg)?;
+    record.set_status(status);
+    if status.is_paid() && record.paid_date.is_none() {
+        record.paid_date = Some(record.payments.last().map(|p| p.date).unwrap_or_else(|| Local::now().date_naive()));
+    }
+
+    let base_stem = InvoiceStatus::base_stem(&stem);
+    let parent = typ_path.parent().unwrap();
+    let new_typ_path = parent.join(format!("{}.typ", base_stem));
+    let new_pdf_path = parent.join(format!("{}.pdf", base_stem));
+    let compiled = write_printed_invoice(root, &new_typ_path, &content, &record, sender)?;
+    // The old files go only once the renamed .typ and its sidecar are written
+    if new_typ_path != typ_path {
+        say!("♻️  Renamed to: {}", base_stem);
+        fs::remove_file(typ_path).ok();
+        fs::remove_file(typ_path.with_extension("pdf")).ok();
+        update_index(&root.join("output"), typ_path);
+    }
+
+    if compiled {
+        Ok(Some(new_pdf_path))
+    } else {
+        say!("❌ Re-compilation failed.");
+        Ok(None)
+    }
+}
+
+// Write a printed .typ with its status fields set from `record`, along with the sidecar,
+// and compile it to the PDF next to it. False when compilation failed.
+fn write_printed_invoice(root: &Path, typ_path: &Path, content: &str, record: &InvoiceRecord, sender: &SenderConfig) -> anyhow::Result<bool> {
+    let existed = typ_path.exists();
+    fs::write(typ_path, set_status_fields(content, record.is_paid, record.is_void))
+        .with_context(|| format!("Failed to write {:?}", typ_path))?;
+    // Put the .typ back as it was, so it never disagrees with the sidecar
+    if let Err(e) = write_sidecar(typ_path, record) {
+        if existed { fs::write(typ_path, content).ok(); } else { fs::remove_file(typ_path).ok(); }
+        return Err(e);
+    }
+    update_index(&root.join("output"), typ_path);
+
+    let pdf_path = typ_path.with_extension("pdf");
+    if typst_installed() {
+        say!("\n🔨 Compiling PDF...");
+        Ok(compile_typst(root, typ_path, &pdf_path).map_err(|e| print_typst_error(&e)).is_ok())
+    } else {
+        say!("\n⚠️  'typst' is not installed, using the built-in PDF layout (brew install typst for the full template).");
+        match printed_context(typ_path, content, record, record.status(), sender) {
+            Ok(context_data) => Ok(render_builtin_pdf(&context_data, &pdf_path)),
+            Err(e) => { say!("❌ {}", e); Ok(false) }
+        }
+    }
+}
+
+// Context rebuilt from the parties, sender and tax label printed on the .typ and the
+// amounts in the sidecar record, for invoices whose client or project may be gone
+fn printed_context(typ_path: &Path, content: &str, record: &InvoiceRecord, status: InvoiceStatus, sender: &SenderConfig) -> Result<InvoiceContext, String> {
+    let source = parse_typ_source(typ_path, content, &sender.date_format)?;
+    let mut sender = sender.clone();
+    apply_typ_sender(&mut sender, content);
+    let mut context_data = build_invoice_context(
+        &record.id, source.printed_date, &sender, &source.client, &source.project, &record_input(record), status,
+    );
+    context_data.subtotal = record.subtotal;
+    context_data.discount_amount = record.discount_amount;
+    context_data.tax_amount = record.tax_amount;
+    context_data.total = record.total;
+    if let Some(label) = printed_tax_label(content) {
+        context_data.tax_label = label;
+    }
+    Ok(context_data)
+}
+
+fn record_payment(root: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let Some(typ_path) = select_invoice(root, &[InvoiceStatus::Unpaid], "Select Invoice to Record a Payment:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    let Some(mut record) = read_sidecar(&typ_path) else {
+        say!("❌ This invoice has no sidecar data; use `pay` to mark it PAID instead.");
+        return Ok(());
+    };
+    if record.is_estimate || record.credit_for.is_some() {
+        say!("❌ Payments can only be recorded against invoices.");
+        return Ok(());
+    }
+
+    say!("Total: {}  Paid: {}  Balance: {}",
+        sender.format_money(record.total), sender.format_money(record.amount_paid()), sender.format_money(record.balance()));
+
+    let amount_str = Text::new(&format!("Payment Amount ({}):", sender.symbol().trim()))
+        .with_default(&format!("{:.2}", record.balance()))
+        .with_validator(validate_amount)
+        .prompt()?;
+    let amount = match parse_amount(&amount_str) {
+        Ok(a) if a > 0.0 => (a * 100.0).round() / 100.0,
+        _ => { say!("❌ Payment amount must be greater than zero."); return Ok(()); }
+    };
+
+    let date = DateSelect::new("Payment Date:")
+        .with_default(Local::now().date_naive())
+        .prompt()?;
+
+    record.payments.push(Payment { amount, date });
+    write_sidecar(&typ_path, &record)?;
+
+    let balance = record.balance();
+    if balance <= 0.0 {
+        say!("✅ Payment recorded. Balance settled.");
+        if let Some(pdf_path) = set_invoice_status(root, &typ_path, InvoiceStatus::Paid, sender)? {
+            say!("✅ Invoice marked as PAID: {:?}", pdf_path);
+        }
+    } else {
+        say!("✅ Payment recorded. Remaining balance: {}", sender.format_money(balance));
+    }
+    Ok(())
+}
+
+fn change_invoice_status(root: &Path, target: InvoiceStatus, sender: &SenderConfig) -> anyhow::Result<()> {
+    // Pay: select only unpaid / Unpay: select only paid
+    let from = if target.is_paid() { InvoiceStatus::Unpaid } else { InvoiceStatus::Paid };
+    let prompt = format!("Select Invoice to Mark as {}:", target.label());
+
+    if let Some(typ_path) = select_invoice(root, &[from], &prompt)
+        && let Some(pdf_path) = set_invoice_status(root, &typ_path, target, sender)?
+    {
+        say!("✅ Done!");
+        open_and_reveal(&pdf_path);
+    }
+    Ok(())
+}
+
+fn void_invoice(root: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    // Paid invoices can be voided too (e.g. refunded work)
+    let from = [InvoiceStatus::Unpaid, InvoiceStatus::Paid];
+
+    if let Some(typ_path) = select_invoice(root, &from, "Select Invoice to VOID:")
+        && let Some(pdf_path) = set_invoice_status(root, &typ_path, InvoiceStatus::Void, sender)?
+    {
+        say!("✅ Done! Invoice marked as VOID.");
+        open_and_reveal(&pdf_path);
+    }
+    Ok(())
+}
+
+// Undo a void, back to PAID or UNPAID depending on the state before voiding
+fn unvoid_invoice(root: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let Some(typ_path) = select_invoice(root, &[InvoiceStatus::Void], "Select Invoice to UNVOID:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    // Legacy voided files keep their pre-void status as a `_PAID_VOID` filename suffix
+    let stem = typ_path.file_stem().unwrap().to_string_lossy().to_string();
+    let was_paid = read_sidecar(&typ_path).is_some_and(|r| r.is_paid) || InvoiceStatus::before_void(&stem).is_paid();
+    let status = if was_paid { InvoiceStatus::Paid } else { InvoiceStatus::Unpaid };
+
+    if let Some(pdf_path) = set_invoice_status(root, &typ_path, status, sender)? {
+        say!("✅ Done! Invoice restored as {}.", status.label());
+        open_and_reveal(&pdf_path);
+    }
+    Ok(())
+}
+
+fn delete_invoice(root: &Path, force: bool) {
+    // PAID invoices are protected unless --force is given
+    let mut from = vec![InvoiceStatus::Unpaid, InvoiceStatus::Void];
+    if force {
+        from.push(InvoiceStatus::Paid);
+    }
+
+    let Some(typ_path) = select_invoice(root, &from, "Select Invoice to DELETE:") else { return };
+
+    let output_dir = root.join("output");
+    let files: Vec<PathBuf> = [Some(typ_path.clone()), Some(typ_path.with_extension("pdf")), sidecar_path(&typ_path), payment_qr_path(&typ_path)]
+        .into_iter()
+        .flatten()
+        .filter(|p| p.exists())
+        .collect();
+    let preview: Vec<String> = files.iter()
+        .map(|p| format!("Delete {}", p.strip_prefix(&output_dir).unwrap_or(p).to_string_lossy()))
+        .collect();
+
+    if !confirm_destructive(root, &preview, &files) {
+        return;
+    }
+
+    for file in &files {
+        let relative = file.strip_prefix(&output_dir).unwrap_or(file).to_string_lossy();
+        match fs::remove_file(file) {
+            Ok(_) => say!("🗑️  Deleted: {}", relative),
+            Err(e) => say_err!("❌ Failed to delete {}: {}", relative, e),
+        }
+    }
+}
+
+// ==========================================
+// 5. List Logic
+// ==========================================
+
+// Newest first, as a table; `limit` keeps only the most recent invoices
+fn list_invoices_by_status(root: &Path, status: InvoiceStatus, limit: Option<usize>, sender: &SenderConfig) {
+    let output_dir = root.join("output");
+    say!("--- List of {} Invoices ---", status.label());
+
+    // PDFs whose .typ is missing or unreadable are still listed, with "?" for what's unknown
+    let mut found: Vec<(PathBuf, Option<InvoiceInfo>, Option<InvoiceRecord>)> = collect_files(&output_dir, "pdf")
+        .into_iter()
+        .filter(|path| invoice_status(path) == status && !is_estimate(path))
+        .map(|path| (path.clone(), invoice_info(&path.with_extension("typ")), read_sidecar(&path)))
+        .collect();
+    if found.is_empty() {
+        say!("(None found)");
+        return;
+    }
+    // Newest first, unreadable ones last
+    found.sort_by(|(path_a, a, _), (path_b, b, _)| match (a, b) {
+        (Some(a), Some(b)) => b.date.cmp(&a.date).then_with(|| b.id.cmp(&a.id)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => path_a.cmp(path_b),
+    });
+
+    let total_count = found.len();
+    found.truncate(limit.unwrap_or(total_count));
+
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("Date"),
+        Cell::new("Client"),
+        Cell::new("ID"),
+        Cell::new("Amount"),
+        Cell::new(if status == InvoiceStatus::Paid { "Paid On" } else { "Sent" }),
+    ]);
+    for (path, info, record) in &found {
+        let note = match status {
+            InvoiceStatus::Paid => record.as_ref().and_then(|r| r.paid_date).map(|d| sender.format_date(d)).unwrap_or_default(),
+            // Unpaid invoices show whether they actually went out (Send or MarkSent)
+            InvoiceStatus::Unpaid => match record.as_ref().and_then(|r| r.sent_at) {
+                Some(sent_at) => sender.format_date(sent_at.date()),
+                None => "not sent".to_string(),
+            },
+            InvoiceStatus::Void => String::new(),
+        };
+        let row = match info {
+            Some(info) => [sender.format_date(info.date), info.client.clone(), info.id.clone(), sender.format_money(info.total)],
+            None => [
+                "?".to_string(),
+                invoice_client_id(path).unwrap_or_else(|| "?".to_string()),
+                invoice_id_from_path(path).unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string()),
+                "?".to_string(),
+            ],
+        };
+        table.add_row(row.into_iter().map(Cell::new).chain([Cell::new(note)]));
+    }
+
+    say!("{table}");
+    if found.len() < total_count {
+        say!("Showing {} of {} invoice(s); use --limit to see more.", found.len(), total_count);
+    } else {
+        say!("{} invoice(s) found.", total_count);
+    }
+}
+
+// ==========================================
+// Edit / Duplicate Logic
+// ==========================================
+
+const ADD_ITEM_OPT: &str = "➕ Add Item";
+const EDIT_ITEM_OPT: &str = "✏️  Edit Item";
+const REMOVE_ITEM_OPT: &str = "🗑️  Remove Item";
+const DONE_OPT: &str = "✅ Done";
+
+// Invoice data recovered from a rendered .typ file
+struct InvoiceSource {
+    invoice_id: String,
+    client_id: String,
+    client: ClientConfig,
+    project: Project,
+    input: InvoiceInput,
+    printed_date: NaiveDate,
+}
+
+// Last capture of `re` in `content`. The template's own parameter defaults
+// (e.g. `tax_rate: 0.0`) come first, the injected invoice data comes last.
+fn last_capture(re: &Regex, content: &str) -> Option<String> {
+    re.captures_iter(content).last().map(|c| c[1].to_string())
+}
+
+// Invoice date encoded in the ID: HI20251214-01 -> 2025-12-14
+fn invoice_id_date(invoice_id: &str) -> Option<NaiveDate> {
+    let caps = Regex::new(r"(\d{8})-\d+$").unwrap().captures(invoice_id)?;
+    NaiveDate::parse_from_str(&caps[1], "%Y%m%d").ok()
+}
+
+// Client ID, invoice ID and project ID of a .typ: the sidecar knows them;
+// legacy filenames are always <invoice_id>_<project_id>[_STATUS]
+fn invoice_names(typ_path: &Path) -> Result<(String, String, String), String> {
+    let client_id = invoice_client_id(typ_path).ok_or("Invoice is not inside a client folder")?;
+    match read_sidecar(typ_path) {
+        Some(record) => Ok((client_id, record.id, record.project_id)),
+        None => {
+            let stem = typ_path.file_stem().unwrap().to_string_lossy().to_string();
+            let name_re = Regex::new(r"^(.+?-\d+)_(.+)$").unwrap();
+            let caps = name_re.captures(InvoiceStatus::base_stem(&stem)).ok_or("Unrecognized invoice filename")?;
+            Ok((client_id, caps[1].to_string(), caps[2].to_string()))
+        }
+    }
+}
+
+// Date printed on the invoice. `\b` keeps `due_date:` from matching;
+// invoices printed before `date_format` use %m/%d/%Y
+fn printed_date(content: &str, date_format: &str) -> Option<NaiveDate> {
+    last_capture(&Regex::new(r#"\bdate:\s*"([^"]+)""#).unwrap(), content)
+        .and_then(|d| parse_printed_date(&d, date_format))
+}
+
+// A date as printed with the sender's date_format, or the original %m/%d/%Y
+fn parse_printed_date(date: &str, date_format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, date_format).or_else(|_| NaiveDate::parse_from_str(date, "%m/%d/%Y")).ok()
+}
+
+// Invoice input as recorded in the sidecar
+fn record_input(record: &InvoiceRecord) -> InvoiceInput {
+    InvoiceInput {
+        items: record.items.clone(),
+        date: record.date,
+        tax_rate: record.tax_rate,
+        tax_status: record.tax_status.clone(),
+        second_tax: record.second_tax.clone(),
+        tax_inclusive: record.tax_inclusive,
+        discount: record.discount,
+        terms: record.terms.clone(),
+        due_date: record.due_date,
+        copy_labels: record.copy_labels.clone(),
+        is_estimate: record.is_estimate,
+        credit_for: record.credit_for.clone(),
+        notes: record.notes.clone(),
+        template: record.template.clone(),
+    }
+}
+
+// Invoice data for editing: the sidecar (or the .typ of legacy invoices) plus the
+// client and project as they are configured now
+fn load_invoice_source(root: &Path, typ_path: &Path, date_format: &str) -> Result<InvoiceSource, String> {
+    let content = fs::read_to_string(typ_path).map_err(|e| format!("Failed to read invoice: {}", e))?;
+    let (client_id, invoice_id, project_id) = invoice_names(typ_path)?;
+
+    let config_path = root.join("data/clients").join(&client_id).join("info.toml");
+    let client_content = fs::read_to_string(&config_path)
+        .map_err(|_| format!("Client '{}' not found", client_id))?;
+    let client: ClientConfig = toml::from_str(&client_content).map_err(|e| format!("TOML parsing failed: {}", e))?;
+    let project = client.projects.iter()
+        .find(|p| p.id == project_id)
+        .cloned()
+        .ok_or_else(|| format!("Project '{}' not found for client '{}'", project_id, client_id))?;
+
+    if let Some(record) = read_sidecar(typ_path) {
+        return Ok(InvoiceSource {
+            invoice_id,
+            client_id,
+            client,
+            project,
+            input: record_input(&record),
+            printed_date: printed_date(&content, date_format).unwrap_or(record.date),
+        });
+    }
+
+    let source = parse_typ_source(typ_path, &content, date_format)?;
+    Ok(InvoiceSource { client, project, ..source })
+}
+
+// One Typst string literal; the capture still needs typst_unescape_str
+const TYP_STR: &str = r#""((?:[^"\\]|\\.)*)""#;
+// Typst markup inside [...]
+const TYP_MARKUP: &str = r"\[((?:[^\]\\]|\\.)*)\]";
+
+// Reverse of typst_escape_str. Other escapes are kept: legacy files injected the
+// app's `\n` line-break markup unescaped.
+fn typst_unescape_str(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next)) if next == '\\' || next == '"' => {
+                out.push(next);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Address from the `[street \ street2 \ city, ST zip \ country]` markup of a .typ
+fn parse_typ_address(markup: &str) -> Address {
+    // `\` followed by whitespace is a line break; any other `\x` is an escaped character
+    let lines: Vec<String> = Regex::new(r"\\\s+").unwrap()
+        .split(markup.trim())
+        .map(|l| Regex::new(r"\\(\S)").unwrap().replace_all(l.trim(), "$1").to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    let city_re = Regex::new(r"^(.*), (\S*) (\S*)$").unwrap();
+    let joined = |lines: &[String]| Some(lines.join(", ")).filter(|l| !l.is_empty());
+
+    let mut address = Address {
+        street: lines.first().cloned().unwrap_or_default(),
+        street2: None,
+        city: String::new(),
+        state: String::new(),
+        zip: String::new(),
+        country: None,
+    };
+    let rest = lines.get(1..).unwrap_or_default();
+    match rest.iter().position(|l| city_re.is_match(l)) {
+        Some(i) => {
+            let caps = city_re.captures(&rest[i]).unwrap();
+            address.city = caps[1].to_string();
+            address.state = caps[2].to_string();
+            address.zip = caps[3].to_string();
+            address.street2 = joined(&rest[..i]);
+            address.country = joined(&rest[i + 1..]);
+        }
+        None => address.street2 = joined(rest),
+    }
+    address
+}
+
+// The injected invoice call at the end of a .typ; the template's own defaults come before it
+fn injected_call(content: &str) -> &str {
+    let mut offset = 0;
+    let mut call_start = None;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with('#') && !["#let", "#set", "#show", "#import"].iter().any(|k| line.starts_with(k)) {
+            call_start = Some(offset);
+        }
+        offset += line.len();
+    }
+    call_start.map_or("", |start| &content[start..])
+}
+
+// Inside of the `key: (...)` block of the injected invoice call
+fn typ_block<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let call = injected_call(content);
+    let start = Regex::new(&format!(r"\b{}:\s*\(", key)).unwrap().find(call)?.end();
+    // Parentheses in strings and markup don't count; both escape their delimiters
+    let (mut depth, mut closing, mut escaped) = (0, None, false);
+    for (i, c) in call[start..].char_indices() {
+        match (c, closing) {
+            _ if escaped => escaped = false,
+            ('\\', _) => escaped = true,
+            (c, Some(end)) if c == end => closing = None,
+            (_, Some(_)) => {}
+            ('"', None) => closing = Some('"'),
+            ('[', None) => closing = Some(']'),
+            ('(', None) => depth += 1,
+            (')', None) if depth == 0 => return Some(&call[start..start + i]),
+            (')', None) => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// `field: "..."` of a block, unescaped; None when missing or `none`
+fn typ_str_field(block: &str, field: &str) -> Option<String> {
+    let re = Regex::new(&format!(r"\b{}:\s*{}", field, TYP_STR)).unwrap();
+    re.captures(block).map(|c| typst_unescape_str(&c[1]))
+}
+
+// `field: [...]` of a block, as raw markup
+fn typ_markup_field<'a>(block: &'a str, field: &str) -> Option<&'a str> {
+    let re = Regex::new(&format!(r"\b{}:\s*{}", field, TYP_MARKUP)).unwrap();
+    re.captures(block).and_then(|c| c.get(1)).map(|m| m.as_str())
+}
+
+// Client and project as printed on the invoice
+fn parse_typ_parties(content: &str, client_id: &str, project_id: &str) -> (ClientConfig, Project) {
+    let client_block = typ_block(content, "client").unwrap_or_default();
+    let client = ClientConfig {
+        schema_version: SCHEMA_VERSION,
+        name: typ_str_field(client_block, "name").unwrap_or_else(|| client_id.to_string()),
+        attn: typ_str_field(client_block, "attn"),
+        email: typ_str_field(client_block, "email"),
+        billing_address: typ_markup_field(client_block, "address").map(parse_typ_address),
+        projects: Vec::new(),
+        recurring: Vec::new(),
+        default_tax_rate: None,
+        tax_exempt: false,
+    };
+    let project_block = typ_block(content, "project").unwrap_or_default();
+    let project = Project {
+        id: project_id.to_string(),
+        name: typ_str_field(project_block, "name"),
+        address: parse_typ_address(typ_markup_field(project_block, "address").unwrap_or_default()),
+        tax_rate: None,
+    };
+    (client, project)
+}
+
+// Overwrite the sender's letterhead fields with the ones printed on the invoice
+fn apply_typ_sender(sender: &mut SenderConfig, content: &str) {
+    let Some(block) = typ_block(content, "sender") else { return };
+    let fields = [
+        ("name", &mut sender.name),
+        ("address1", &mut sender.address1),
+        ("address2", &mut sender.address2),
+        ("license", &mut sender.license),
+        ("email", &mut sender.email),
+        ("phone", &mut sender.phone),
+    ];
+    for (field, value) in fields {
+        if let Some(printed) = typ_str_field(block, field) {
+            *value = printed;
+        }
+    }
+}
+
+// Invoice data recovered from the rendered .typ alone. Needs no client config, so it
+// also works for invoices of clients and projects that were deleted or renamed since.
+fn parse_typ_source(typ_path: &Path, content: &str, date_format: &str) -> Result<InvoiceSource, String> {
+    let (client_id, invoice_id, project_id) = invoice_names(typ_path)?;
+    let (client, project) = parse_typ_parties(content, &client_id, &project_id);
+
+    let printed_date = printed_date(content, date_format).unwrap_or_else(|| Local::now().date_naive());
+    let date = invoice_id_date(&invoice_id).unwrap_or(printed_date);
+
+    // Items: (desc: "...", quantity: 1, rate: 10, amount: 10, taxable: true, unit: "hours") or legacy (desc: "...", amount: 10)
+    let item_re = Regex::new(&format!(
+        r"\(desc:\s*{S},\s*(?:quantity:\s*([\d\.]+),\s*rate:\s*([\d\.]+),\s*)?amount:\s*([\d\.]+)(?:,\s*taxable:\s*(true|false))?(?:,\s*unit:\s*{S})?\)",
+        S = TYP_STR,
+    )).unwrap();
+    let items = item_re.captures_iter(content).map(|cap| {
+        let amount: f64 = cap[4].parse().unwrap_or(0.0);
+        InvoiceItem {
+            description: typst_unescape_str(&cap[1]),
+            quantity: cap.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(1.0),
+            rate: cap.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(amount),
+            amount,
+            taxable: cap.get(5).is_none_or(|m| m.as_str() == "true"),
+            unit: cap.get(6).map(|m| typst_unescape_str(m.as_str())).filter(|u| !u.is_empty()),
+        }
+    }).collect();
+
+    let tax_rate = last_capture(&Regex::new(r"tax_rate:\s*([\d\.]+)").unwrap(), content)
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(0.0);
+    let tax_status = last_capture(&Regex::new(&format!(r"tax_display:\s*{}", TYP_STR)).unwrap(), content)
+        .map(|t| typst_unescape_str(&t))
+        .unwrap_or_else(|| "Exempt".to_string());
+
+    let copy_labels = last_capture(&Regex::new(r"copy_labels:\s*\(([^)]*)\)").unwrap(), content)
+        .map(|list| {
+            Regex::new(TYP_STR).unwrap()
+                .captures_iter(&list)
+                .map(|c| typst_unescape_str(&c[1]))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Legacy invoices predate discounts and payment terms
+    let is_estimate = invoice_id.starts_with(ESTIMATE_PREFIX);
+    let input = InvoiceInput { items, date, tax_rate, tax_status, second_tax: None, tax_inclusive: false, discount: None, terms: String::new(), due_date: None, copy_labels, is_estimate, credit_for: None, notes: None, template: None };
+    Ok(InvoiceSource { invoice_id, client_id, client, project, input, printed_date })
+}
+
+fn edit_invoice(root: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let from = [InvoiceStatus::Unpaid, InvoiceStatus::Paid];
+    let Some(typ_path) = select_invoice(root, &from, "Select Invoice to Edit:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    let mut source = load_invoice_source(root, &typ_path, &sender.date_format).map_err(anyhow::Error::msg)?;
+
+    edit_items_loop(&mut source.input.items, sender)?;
+
+    if source.input.items.is_empty() {
+        say!("❌ An invoice needs at least one item. Aborting.");
+        return Ok(());
+    }
+
+    // Same invoice_id, filename and status so the numbering doesn't shift
+    let status = invoice_status(&typ_path);
+    let context_data = build_invoice_context(
+        &source.invoice_id, source.printed_date, sender, &source.client, &source.project, &source.input, status,
+    );
+    let mut record = InvoiceRecord::from_context(
+        &context_data, &source.client_id, source.input.date, source.input.due_date, &source.input.tax_status, source.input.discount,
+    );
+    record.template = source.input.template.clone();
+    record.second_tax = source.input.second_tax.clone();
+    if let Some(previous) = read_sidecar(&typ_path) {
+        record.payments = previous.payments;
+        record.paid_date = previous.paid_date;
+        record.sent_at = previous.sent_at;
+    }
+
+    let pdf_path = typ_path.with_extension("pdf");
+    if render_invoice(root, &context_data, &record, &typ_path, &pdf_path)? {
+        say!("✅ Invoice updated: {:?}", pdf_path);
+        open_and_reveal(&pdf_path);
+    }
+    Ok(())
+}
+
+fn duplicate_invoice(root: &Path, data_dir: &Path, sender: &SenderConfig, invoice_prefix: &str) -> anyhow::Result<()> {
+    let from = [InvoiceStatus::Unpaid, InvoiceStatus::Paid, InvoiceStatus::Void];
+    let Some(typ_path) = select_invoice(root, &from, "Select Invoice to Duplicate:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    let source = load_invoice_source(root, &typ_path, &sender.date_format).map_err(anyhow::Error::msg)?;
+
+    // Optionally retarget the copy to another client / project
+    let retarget = Confirm::new("Bill a different client or project?")
+        .with_default(false)
+        .prompt()?;
+    let (client_id, client, project) = if retarget {
+        let client_id = select_or_create_client(data_dir)?;
+        let (client, project) = select_or_create_project(data_dir, &client_id)?;
+        (client_id, client, project)
+    } else {
+        (source.client_id, source.client, source.project)
+    };
+
+    let mut input = source.input;
+    let adjust = Confirm::new("Adjust items before generating?")
+        .with_default(false)
+        .prompt()?;
+    if adjust {
+        edit_items_loop(&mut input.items, sender)?;
+    }
+
+    if input.items.is_empty() {
+        say!("❌ No items entered. Aborting.");
+        return Ok(());
+    }
+
+    // New invoice: today's date, fresh ID, always UNPAID; keep the same payment window
+    let date = Local::now().date_naive();
+    input.due_date = input.due_date.map(|due| date + (due - input.date));
+    input.date = date;
+    generate_pdf(root, &client_id, &client, &project, &input, sender, invoice_prefix)?;
+    Ok(())
+}
+
+fn credit_invoice(root: &Path, sender: &SenderConfig, invoice_prefix: &str) -> anyhow::Result<()> {
+    let from = [InvoiceStatus::Unpaid, InvoiceStatus::Paid];
+    let Some(typ_path) = select_invoice(root, &from, "Select Invoice to Credit:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+
+    let source = load_invoice_source(root, &typ_path, &sender.date_format).map_err(anyhow::Error::msg)?;
+    if source.input.is_estimate || source.input.credit_for.is_some() {
+        say!("❌ Only regular invoices can be credited.");
+        return Ok(());
+    }
+
+    say!("\n--- Credit Note for {} ---", source.invoice_id);
+    say!("💡 Amounts are credited, so they are recorded as negative.");
+    let mut items = enter_invoice_items(sender, &[])?;
+    if items.is_empty() {
+        say!("❌ No items entered. Aborting.");
+        return Ok(());
+    }
+    for item in &mut items {
+        item.rate = -item.rate.abs();
+        item.amount = -item.amount.abs();
+    }
+
+    // Same tax treatment as the credited invoice, dated today, not payable
+    let input = InvoiceInput {
+        items,
+        date: Local::now().date_naive(),
+        discount: None,
+        notes: None,
+        terms: String::new(),
+        due_date: None,
+        is_estimate: false,
+        credit_for: Some(source.invoice_id.clone()),
+        ..source.input
+    };
+    generate_pdf(root, &source.client_id, &source.client, &source.project, &input, sender, invoice_prefix)?;
+    Ok(())
+}
+
+fn edit_items_loop(items: &mut Vec<InvoiceItem>, sender: &SenderConfig) -> anyhow::Result<()> {
+    loop {
+        print_items_table("Current Items", items, sender);
+
+        let mut actions = vec![ADD_ITEM_OPT];
+        if !items.is_empty() {
+            actions.push(EDIT_ITEM_OPT);
+            actions.push(REMOVE_ITEM_OPT);
+        }
+        actions.push(DONE_OPT);
+
+        let action = Select::new("What would you like to do?", actions).prompt()?;
+
+        match action {
+            ADD_ITEM_OPT => {
+                if let ItemPrompt::Item(item) = prompt_invoice_item(None, sender)? {
+                    items.push(item);
+                }
+            }
+            EDIT_ITEM_OPT => {
+                if let Some(idx) = select_item_index(items, "Select Item to Edit:")
+                    && let ItemPrompt::Item(item) = prompt_invoice_item(Some(&items[idx]), sender)?
+                {
+                    items[idx] = item;
+                }
+            }
+            REMOVE_ITEM_OPT => {
+                if let Some(idx) = select_item_index(items, "Select Item to Remove:") {
+                    let removed = items.remove(idx);
+                    say!("🗑️  Removed: {}", removed.description);
+                }
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+fn select_item_index(items: &[InvoiceItem], prompt: &str) -> Option<usize> {
+    let options: Vec<String> = items.iter()
+        .enumerate()
+        .map(|(i, item)| format!("{}. {}", i + 1, item.description))
+        .collect();
+    let choice = Select::new(prompt, options.clone()).prompt().ok()?;
+    options.iter().position(|o| o == &choice)
+}
+
+// ==========================================
+// 6. Open Folder Logic
+// ==========================================
+
+// Any invoice PDF under output/, newest first, labeled by its path relative to output/
+fn open_invoice_wizard(root: &Path) {
+    let output_dir = root.join("output");
+    let mut files = collect_files(&output_dir, "pdf");
+    if files.is_empty() {
+        say!("❌ No invoice PDFs found.");
+        return;
+    }
+    // Newest invoice date first; PDFs without one (statements) go last
+    files.sort_by_cached_key(|p| {
+        let date = read_sidecar(p).map(|r| r.date).or_else(|| invoice_id_from_path(p).and_then(|id| invoice_id_date(&id)));
+        std::cmp::Reverse((date, std::fs::metadata(p).and_then(|m| m.modified()).ok()))
+    });
+
+    let options: Vec<String> = files.iter()
+        .map(|p| p.strip_prefix(&output_dir).unwrap_or(p).to_string_lossy().to_string())
+        .collect();
+
+    // Asked for explicitly, so --no-open / auto_open = false don't apply
+    match Select::new("Select Invoice to Open (Type to Filter):", options).with_page_size(10).prompt() {
+        Ok(choice) => open_path(&output_dir.join(choice)),
+        Err(_) => say!("Operation cancelled."),
+    }
+}
+
+fn open_folder_wizard(root: &Path) {
+    let output_root = root.join("output");
+    let mut options = Vec::new();
+    
+    let root_opt = "📂 Open Root Output Directory".to_string();
+    options.push(root_opt.clone());
+
+    if output_root.exists()
+        && let Ok(years) = fs::read_dir(&output_root)
+    {
+        for year_entry in years.flatten() {
+            if year_entry.path().is_dir() {
+                let year_name = year_entry.file_name().to_string_lossy().to_string();
+                if let Ok(clients) = fs::read_dir(year_entry.path()) {
+                    for client_entry in clients.flatten() {
+                        if client_entry.path().is_dir() {
+                            let client_name = client_entry.file_name().to_string_lossy().to_string();
+                            options.push(format!("{} / {}", year_name, client_name));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut client_paths: Vec<String> = options.drain(1..).collect();
+    client_paths.sort();
+    client_paths.reverse();
+    
+    let mut final_options = vec![root_opt.clone()];
+    final_options.extend(client_paths);
+
+    match Select::new("Select Folder to Open:", final_options).prompt() {
+        Ok(choice) => {
+            let target_path = if choice == root_opt {
+                output_root
+            } else {
+                let parts: Vec<&str> = choice.split(" / ").collect();
+                if parts.len() == 2 {
+                    output_root.join(parts[0]).join(parts[1])
+                } else {
+                    output_root
+                }
+            };
+            say!("🚀 Opening: {:?}", target_path);
+            open_path(&target_path);
+        },
+        Err(_) => say!("Operation cancelled."),
+    }
+}
+
+// ==========================================
+// Search Logic
+// ==========================================
+
+fn search_invoices(root: &Path) {
+    let output_root = root.join("output");
+    if !output_root.exists() {
+        say!("❌ No output directory found.");
+        return;
+    }
+
+    say!("🔍 Loading invoices...");
+
+    // Gather all .typ files
+    let mut typ_files = Vec::new();
+    let mut stack = vec![output_root.clone()];
+    while let Some(dir) = stack.pop() {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    stack.push(path);
+                } else if path.extension().is_some_and(|e| e == "typ") {
+                    typ_files.push(path);
+                }
+            }
+        }
+    }
+
+    if typ_files.is_empty() {
+        say!("No invoices found.");
+        return;
+    }
+
+    // Build searchable entries: (display_string, path)
+    // Display string includes all searchable info so Select's built-in filter works
+    let client_re = Regex::new(r#"client:\s*\(\s*name:\s*"([^"]+)""#).unwrap();
+    let project_re = Regex::new(r#"project:\s*\([^)]*address:\s*\[([^\]]+)\]"#).unwrap();
+    let desc_re = Regex::new(r#"desc:\s*"([^"]+)""#).unwrap();
+    let amount_re = Regex::new(r"amount:\s*([\d\.]+)").unwrap();
+
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+
+    for path in typ_files {
+        let rel_path = path.strip_prefix(&output_root).unwrap_or(&path).to_string_lossy().to_string();
+        
+        let mut search_parts = vec![rel_path.clone()];
+
+        if let Ok(content) = fs::read_to_string(&path) {
+            // Extract client name
+            if let Some(cap) = client_re.captures(&content) {
+                search_parts.push(cap[1].to_string());
+            }
+            // Extract project address
+            if let Some(cap) = project_re.captures(&content) {
+                search_parts.push(cap[1].replace("\\", " ").replace("  ", " "));
+            }
+            // Extract all descriptions
+            for cap in desc_re.captures_iter(&content) {
+                search_parts.push(cap[1].to_string());
+            }
+            // Extract all amounts
+            for cap in amount_re.captures_iter(&content) {
+                search_parts.push(format!("${}", &cap[1]));
+            }
+        }
+
+        // Build display: "path | client | project | desc... | $amt"
+        let display = search_parts.join(" | ");
+        entries.push((display, path));
+    }
+
+    // Sort by modified time desc
+    entries.sort_by_key(|(_, p)| std::fs::metadata(p).and_then(|m| m.modified()).ok());
+    entries.reverse();
+
+    let options: Vec<String> = entries.iter().map(|(d, _)| d.clone()).collect();
+
+    // Use Select with real-time type-to-filter
+    let selection = Select::new("Search Invoice (type to filter):", options.clone())
+        .with_page_size(15)
+        .prompt();
+
+    match selection {
+        Ok(choice) => {
+            if let Some(pos) = options.iter().position(|o| o == &choice) {
+                let path = &entries[pos].1;
+                let pdf_path = path.with_extension("pdf");
+                if pdf_path.exists() {
+                    open_and_reveal(&pdf_path);
+                } else {
+                    open_and_reveal(path);
+                }
+            }
+        },
+        Err(_) => say!("Cancelled"),
+    }
+}
+
+// All filters must match (AND); prints a table instead of the interactive picker
+fn search_invoices_filtered(root: &Path, filters: &SearchArgs, sender: &SenderConfig) {
+    let output_root = root.join("output");
+    if !output_root.exists() {
+        say!("❌ No output directory found.");
+        return;
+    }
+
+    let client_filter = filters.client.as_ref().map(|c| c.to_lowercase());
+    let id_filter = filters.id.as_ref().map(|i| i.to_lowercase());
+
+    let mut matches: Vec<InvoiceInfo> = collect_files(&output_root, "typ")
+        .into_iter()
+        .filter_map(|path| {
+            let info = invoice_info(&path)?;
+            let client_id = invoice_client_id(&path).map(|id| id.to_lowercase()).unwrap_or_default();
+            let keep = client_filter.as_ref().is_none_or(|c| info.client.to_lowercase().contains(c) || client_id.contains(c))
+                && id_filter.as_ref().is_none_or(|i| info.id.to_lowercase().contains(i))
+                && filters.min.is_none_or(|min| info.total >= min)
+                && filters.max.is_none_or(|max| info.total <= max);
+            keep.then_some(info)
+        })
+        .collect();
+
+    if matches.is_empty() {
+        say!("No matching invoices.");
+        return;
+    }
+
+    matches.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.id.cmp(&a.id)));
+
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("ID"),
+        Cell::new("Client"),
+        Cell::new("Date"),
+        Cell::new("Total"),
+        Cell::new("Status"),
+    ]);
+    for info in &matches {
+        let status_cell = match info.status {
+            InvoiceStatus::Paid => Cell::new(info.status.label()).fg(Color::Green),
+            InvoiceStatus::Unpaid => Cell::new(info.status.label()).fg(Color::Red),
+            InvoiceStatus::Void => Cell::new(info.status.label()),
+        };
+        table.add_row(vec![
+            Cell::new(&info.id),
+            Cell::new(&info.client),
+            Cell::new(info.date.format("%m/%d/%Y").to_string()),
+            Cell::new(sender.format_money(info.total)),
+            status_cell,
+        ]);
+    }
+
+    say!("{table}");
+    say!("{} invoice(s) found.", matches.len());
+}
+
+// ==========================================
+// 7. Config & Utilities
+// ==========================================
+
+fn get_config_path() -> PathBuf {
+    if let Some(proj_dirs) = ProjectDirs::from("com", "invoice-maker", "app") {
+        let config_dir = proj_dirs.config_dir();
+        if !config_dir.exists() { fs::create_dir_all(config_dir).ok(); }
+        return config_dir.join("settings.toml");
+    }
+    PathBuf::from("settings.toml")
+}
+
+fn load_settings() -> Option<AppSettings> {
+    let path = get_config_path();
+    if !path.exists() { return None; }
+    let content = fs::read_to_string(&path).ok()?;
+    let mut settings: AppSettings = toml::from_str(&content).ok()?;
+    // Settings added since take their defaults; `migrate` writes them out
+    if schema_outdated(settings.schema_version, &path) {
+        settings.schema_version = SCHEMA_VERSION;
+    }
+    Some(settings)
+}
+
+// schema_version of a config file as written; files from before versioning have none
+fn file_schema_version(path: &Path) -> anyhow::Result<u32> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let table: toml::Table = content.parse().with_context(|| format!("Invalid TOML in {:?}", path))?;
+    Ok(table.get("schema_version").and_then(|v| v.as_integer()).unwrap_or(0) as u32)
+}
+
+// Bring settings.toml, the sender files and every client's info.toml up to
+// SCHEMA_VERSION. Loading upgrades them in memory only; this writes them back.
+fn migrate_configs(root: &Path, data_dir: &Path) -> anyhow::Result<()> {
+    let mut upgraded = 0;
+
+    let settings_path = get_config_path();
+    if settings_path.exists() && file_schema_version(&settings_path)? < SCHEMA_VERSION
+        && let Some(settings) = load_settings()
+    {
+        save_settings(&settings)?;
+        say!("♻️  Upgraded {:?}", settings_path);
+        upgraded += 1;
+    }
+
+    // Sender files are hand-edited, so the version is prepended instead of rewriting
+    // them (and dropping the comments)
+    let sender_files = std::iter::once(root.join("sender.toml"))
+        .chain(sender_profiles(root).into_iter().map(|name| root.join("senders").join(format!("{}.toml", name))));
+    for path in sender_files.filter(|p| p.exists()) {
+        if file_schema_version(&path)? >= SCHEMA_VERSION {
+            continue;
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let content = content.lines()
+            .filter(|l| !l.trim_start().starts_with("schema_version"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(&path, format!("schema_version = {}\n{}\n", SCHEMA_VERSION, content))
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        say!("♻️  Upgraded {:?}", path);
+        upgraded += 1;
+    }
+
+    // Rewriting fills in the defaults of fields added since
+    for client_id in client_ids(data_dir) {
+        let path = data_dir.join(&client_id).join("info.toml");
+        if !path.exists() || file_schema_version(&path)? >= SCHEMA_VERSION {
+            continue;
+        }
+        let config = load_client_config(data_dir, &client_id)?;
+        save_client_config(data_dir, &client_id, &config)?;
+        say!("♻️  Upgraded {:?}", path);
+        upgraded += 1;
+    }
+
+    say!("✅ {} config file(s) upgraded.\n", upgraded);
+    Ok(())
+}
+
+// True when a config file predates SCHEMA_VERSION and should be upgraded.
+// Files from a newer version only get a warning: fields unknown here would be lost on save.
+fn schema_outdated(version: u32, path: &Path) -> bool {
+    if version > SCHEMA_VERSION {
+        say!("⚠️  {:?} has schema_version {}, newer than this version supports ({}).", path, version, SCHEMA_VERSION);
+    }
+    version < SCHEMA_VERSION
+}
+
+fn save_settings(settings: &AppSettings) -> anyhow::Result<()> {
+    let path = get_config_path();
+    let toml_str = toml::to_string_pretty(settings)?;
+    fs::write(&path, toml_str).with_context(|| format!("Failed to save settings to {:?}", path))
+}
+
+// Store the tax rate (in %) as the default for the next invoice; best effort
+fn remember_tax_rate(rate: f64) {
+    let Some(mut settings) = load_settings() else { return };
+    if settings.last_tax_rate == Some(rate) {
+        return;
+    }
+    settings.last_tax_rate = Some(rate);
+    if let Err(e) = save_settings(&settings) {
+        say!("⚠️  Could not remember the tax rate: {:#}", e);
+    }
+}
+
+const DEFAULT_SENDER_TEMPLATE: &str = include_str!("../sender.toml");
+
+// Named profiles live in `senders/<name>.toml`; without any, the single `sender.toml` is used
+fn sender_profiles(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root.join("senders"))
+        .map(|entries| entries.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+// Default sender: the first profile, or sender.toml (created on first run)
+fn load_sender_config(root: &Path) -> anyhow::Result<SenderConfig> {
+    match sender_profiles(root).first() {
+        Some(name) => load_sender_profile(root, name),
+        None => read_sender_file(root, &root.join("sender.toml"), None),
+    }
+}
+
+fn load_sender_profile(root: &Path, name: &str) -> anyhow::Result<SenderConfig> {
+    let path = root.join("senders").join(format!("{}.toml", name));
+    if !path.exists() {
+        anyhow::bail!("Sender profile '{}' not found (available: {})", name, sender_profiles(root).join(", "));
+    }
+    read_sender_file(root, &path, Some(name.to_string()))
+}
+
+// Pick the sender for a new invoice; only prompts when there are several profiles
+fn select_sender(root: &Path, default: &SenderConfig) -> anyhow::Result<SenderConfig> {
+    let profiles = sender_profiles(root);
+    if profiles.len() < 2 {
+        return Ok(default.clone());
+    }
+    let name = Select::new("Sender:", profiles).prompt()?;
+    load_sender_profile(root, &name)
+}
+
+// Sender profile an existing invoice was issued under, falling back to `default`
+fn invoice_sender(root: &Path, typ_path: &Path, default: &SenderConfig) -> SenderConfig {
+    let Some(name) = read_sidecar(typ_path).and_then(|r| r.sender_profile) else { return default.clone() };
+    match load_sender_profile(root, &name) {
+        Ok(sender) => sender,
+        Err(e) => {
+            say!("⚠️  {:#}. Using the default sender.", e);
+            default.clone()
+        }
+    }
+}
+
+fn read_sender_file(root: &Path, path: &Path, profile: Option<String>) -> anyhow::Result<SenderConfig> {
+    let mut sender = if path.exists() {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let mut sender: SenderConfig = toml::from_str(&content).with_context(|| format!("Invalid sender config {:?}", path))?;
+        sender.profile = profile;
+        // Missing fields take their serde defaults; `migrate` stamps the version
+        if schema_outdated(sender.schema_version, path) {
+            sender.schema_version = SCHEMA_VERSION;
+        }
+        // An invalid pattern would panic when the first date is formatted
+        if chrono::format::StrftimeItems::new(&sender.date_format).parse().is_err() {
+            anyhow::bail!("Invalid date_format {:?} in {:?}", sender.date_format, path);
+        }
+        if sender.paper().is_none() {
+            anyhow::bail!("Invalid page_size {:?} in {:?} (use \"Letter\" or \"A4\")", sender.page_size, path);
+        }
+        // Normalized to "#rrggbb" for Typst's rgb()
+        sender.accent_color = match parse_hex_color(&sender.accent_color) {
+            Some((r, g, b)) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            None => {
+                say!("⚠️  Invalid accent_color {:?} in {:?}, using {}.", sender.accent_color, path, DEFAULT_ACCENT_COLOR);
+                DEFAULT_ACCENT_COLOR.to_string()
+            }
+        };
+        sender
+    } else {
+        say!("✨ Initializing default sender configuration...");
+        let mut default_sender: SenderConfig = toml::from_str(DEFAULT_SENDER_TEMPLATE).expect("Failed to parse default sender.toml");
+        default_sender.profile = profile;
+        fs::write(path, DEFAULT_SENDER_TEMPLATE).with_context(|| format!("Failed to write {:?}", path))?;
+        default_sender
+    };
+
+    // logo_path may be relative to the data root
+    if let Some(logo) = &sender.logo_path {
+        let logo = PathBuf::from(expand_home_dir(logo));
+        let logo = if logo.is_absolute() { logo } else { root.join(logo) };
+        sender.logo_path = Some(logo.to_string_lossy().to_string());
+    }
+    Ok(sender)
+}
+
+// Edit Sender Wizard: every SenderConfig field, pre-filled from the file. [smtp] is kept as is.
+fn edit_sender_wizard(root: &Path) -> anyhow::Result<()> {
+    let profiles = sender_profiles(root);
+    let path = if profiles.is_empty() {
+        root.join("sender.toml")
+    } else {
+        let name = Select::new("Select Sender Profile to Edit:", profiles).prompt()?;
+        root.join("senders").join(format!("{}.toml", name))
+    };
+
+    // Parsed directly: read_sender_file would make logo_path absolute
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut sender: SenderConfig = toml::from_str(&content).with_context(|| format!("Invalid sender config {:?}", path))?;
+
+    say!("\n--- Editing Sender: {} ---", path.display());
+    say!("💡 Press Enter to keep a value, or type '-' to clear an optional field.");
+
+    let required = |prompt: &str, current: &str| -> anyhow::Result<String> {
+        let input = Text::new(prompt).with_default(current).prompt()?;
+        Ok(if input.trim().is_empty() { current.to_string() } else { input.trim().to_string() })
+    };
+
+    say!("\n--- Business ---");
+    sender.name = required("Business Name:", &sender.name)?;
+    sender.address1 = prompt_optional("Address Line 1:", Some(&sender.address1))?.unwrap_or_default();
+    sender.address2 = prompt_optional("Address Line 2 (City, State Zip):", Some(&sender.address2))?.unwrap_or_default();
+    sender.license = prompt_optional("License / Tax ID Line (Optional):", Some(&sender.license))?.unwrap_or_default();
+    sender.email = prompt_optional("Email Line (Optional):", Some(&sender.email))?.unwrap_or_default();
+    sender.phone = prompt_optional("Phone Line (Optional):", Some(&sender.phone))?.unwrap_or_default();
+    sender.country = prompt_optional("Home Country (Optional, e.g. US):", sender.country.as_deref())?;
+    sender.logo_path = prompt_optional("Logo Image (Optional, absolute or relative to the data root):", sender.logo_path.as_deref())?;
+
+    say!("\n--- Payment ---");
+    sender.bank_name = prompt_optional("Bank Name (Optional):", sender.bank_name.as_deref())?;
+    sender.account_name = prompt_optional("Account Name (Optional):", sender.account_name.as_deref())?;
+    sender.account_number = prompt_optional("Account Number (Optional):", sender.account_number.as_deref())?;
+    sender.routing_number = prompt_optional("Routing Number (Optional):", sender.routing_number.as_deref())?;
+    sender.iban = prompt_optional("IBAN (Optional):", sender.iban.as_deref())?;
+    sender.swift = prompt_optional("SWIFT / BIC (Optional):", sender.swift.as_deref())?;
+    sender.bank_info = prompt_optional("Free-text Payment Info (Optional, used without bank fields):", Some(&sender.bank_info))?.unwrap_or_default();
+    sender.payment_uri = prompt_optional("Payment Link for a QR Code (Optional):", sender.payment_uri.as_deref())?;
+    sender.terms = prompt_optional("Terms & Conditions (Optional, use '\\n' for new lines):", sender.terms.as_deref())?;
+    sender.footer = prompt_optional("Footer Line (Optional, default \"Thank you for your business!\"):", sender.footer.as_deref())?;
+
+    say!("\n--- Formatting ---");
+    sender.currency = required("Currency Code (e.g. USD, EUR):", &sender.currency)?.to_uppercase();
+    sender.currency_symbol = prompt_optional("Currency Symbol (Optional, overrides the code's):", sender.currency_symbol.as_deref())?;
+    sender.whole_dollar_amounts = Confirm::new("Print whole amounts without cents (e.g. $500)?")
+        .with_default(sender.whole_dollar_amounts)
+        .prompt()?;
+    sender.tax_label = required("Tax Name (e.g. Tax, VAT, GST):", &sender.tax_label)?;
+    sender.tax_inclusive = Confirm::new("Do item prices usually include tax?")
+        .with_default(sender.tax_inclusive)
+        .prompt()?;
+    let modes = vec!["Round tax once on the total", "Round tax on every line"];
+    let per_line = sender.rounding_mode == RoundingMode::PerLine;
+    let mode = Select::new("Tax Rounding:", modes).with_starting_cursor(per_line as usize).raw_prompt()?;
+    sender.rounding_mode = if mode.index == 1 { RoundingMode::PerLine } else { RoundingMode::Total };
+    sender.date_format = Text::new("Date Format (e.g. %m/%d/%Y or %d.%m.%Y):")
+        .with_default(&sender.date_format)
+        .with_validator(|input: &str| Ok(if chrono::format::StrftimeItems::new(input.trim()).parse().is_ok() {
+            Validation::Valid
+        } else {
+            Validation::Invalid("Not a valid strftime pattern.".into())
+        }))
+        .prompt()?
+        .trim()
+        .to_string();
+    let sizes = vec!["Letter", "A4"];
+    let current_size = if sender.paper() == Some("a4") { 1 } else { 0 };
+    sender.page_size = Select::new("Page Size:", sizes).with_starting_cursor(current_size).prompt()?.to_string();
+    sender.accent_color = Text::new("Accent Color (hex, e.g. #0055aa):")
+        .with_default(&sender.accent_color)
+        .with_validator(|input: &str| Ok(if parse_hex_color(input).is_some() {
+            Validation::Valid
+        } else {
+            Validation::Invalid("Please enter a hex color like #0055aa.".into())
+        }))
+        .prompt()?
+        .trim()
+        .to_string();
+
+    if !Confirm::new(&format!("Save to {}? (comments in the file are not kept)", path.display())).with_default(true).prompt()? {
+        say!("⏸️  Nothing saved.");
+        return Ok(());
+    }
+    let toml_str = toml::to_string_pretty(&sender)?;
+    fs::write(&path, toml_str).with_context(|| format!("Failed to write {:?}", path))?;
+    say!("✅ Sender updated: {}", path.display());
+    Ok(())
+}
+
+fn setup_config_wizard() -> anyhow::Result<AppSettings> {
+    say!("\n⚙️  --- Configuration Setup ---");
+    let current = load_settings();
+    let disable_lock = current.as_ref().is_some_and(|s| s.disable_lock);
+    let auto_open = current.as_ref().is_none_or(|s| s.auto_open);
+    let copy_path = current.as_ref().is_some_and(|s| s.copy_path);
+    let output_layout = current.as_ref().map(|s| s.output_layout.clone()).unwrap_or_else(default_output_layout);
+    let filename_pattern = current.as_ref().map(|s| s.filename_pattern.clone()).unwrap_or_else(default_filename_pattern);
+    let last_tax_rate = current.as_ref().and_then(|s| s.last_tax_rate);
+    let default_prefix = current.as_ref().map(|s| s.invoice_prefix.clone()).unwrap_or_else(default_invoice_prefix);
+    let default_typst = current.as_ref().and_then(|s| s.typst_path.clone()).unwrap_or_default();
+    let default_val = current.map(|s| s.data_root).unwrap_or_else(|| "~/Documents/Business".to_string());
+
+    say!("📂 Opening folder picker...");
+    let picked_path = rfd::FileDialog::new()
+        .set_title("Select Root Data Directory")
+        .pick_folder();
+
+    let new_root = if let Some(path) = picked_path {
+        path.to_string_lossy().to_string()
+    } else {
+        say!("❌ No folder selected. Falling back to manual input.");
+        Text::new("Enter Root Data Directory:").with_default(&default_val).prompt()?
+    };
+
+    let prefix_input = Text::new("Invoice ID Prefix (e.g. INV):").with_default(&default_prefix).prompt()?;
+    let invoice_prefix = prefix_input.trim().to_uppercase();
+    let reserved = [ESTIMATE_PREFIX, CREDIT_PREFIX].contains(&invoice_prefix.as_str());
+    let invoice_prefix = if invoice_prefix.is_empty() || reserved || !invoice_prefix.chars().all(|c| c.is_ascii_alphabetic()) {
+        say!("⚠️  Prefix must be letters only (and not {} or {}), using {}.", ESTIMATE_PREFIX, CREDIT_PREFIX, default_prefix);
+        default_prefix
+    } else {
+        invoice_prefix
+    };
+
+    let typst_input = Text::new("Typst binary path (leave empty to use `typst` from PATH):").with_default(&default_typst).prompt()?;
+    let typst_path = Some(typst_input.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(path) = &typst_path
+        && !Path::new(&expand_home_dir(path)).is_file()
+    {
+        say!("⚠️  {} does not exist yet; PDFs will use the built-in layout until it does.", path);
+    }
+
+    let settings = AppSettings { schema_version: SCHEMA_VERSION, data_root: new_root, disable_lock, invoice_prefix, typst_path, auto_open, copy_path, output_layout, filename_pattern, last_tax_rate };
+    save_settings(&settings)?;
+    say!("✅ Settings saved.");
+    Ok(settings)
+}
+
+fn show_config(settings: Option<&AppSettings>) {
+    let yes_no = |b: bool| if b { "✅ yes" } else { "❌ no" };
+    say!("⚙️  Config file:    {}", get_config_path().display());
+
+    let Some(settings) = settings else {
+        say!("❌ Not configured yet. Run `config` to set the data directory.");
+        return;
+    };
+    if let Some(typst) = &settings.typst_path {
+        TYPST_BIN.set(expand_home_dir(typst)).ok();
+    }
+
+    let root = PathBuf::from(expand_home_dir(&settings.data_root));
+    say!("📂 Data root:      {}", settings.data_root);
+    say!("   Expanded:       {}", root.display());
+    say!("   Exists:         {}", yes_no(root.is_dir()));
+    say!("🧾 Invoice prefix: {}", settings.invoice_prefix);
+    say!("🗂️  Output layout:  output/{}", settings.output_layout);
+    say!("🏷️  File names:     {}.pdf", settings.filename_pattern);
+    say!("🔒 Lock file:      {}", if settings.disable_lock { "disabled" } else { "enabled" });
+    say!("📂 Auto-open:      {}", if settings.auto_open { "enabled" } else { "disabled" });
+    say!("📋 Copy path:      {}", if settings.copy_path { "enabled" } else { "disabled" });
+
+    let sender_path = root.join("sender.toml");
+    say!("👤 sender.toml:    {} ({})", yes_no(sender_path.is_file()), sender_path.display());
+    let profiles = sender_profiles(&root);
+    if !profiles.is_empty() {
+        say!("   Profiles:       {}", profiles.join(", "));
+    }
+
+    let typst_bin = TYPST_BIN.get().map(String::as_str).unwrap_or("typst");
+    let typst = if typst_installed() { "✅ found" } else { "❌ not found, using the built-in PDF layout" };
+    say!("🔨 Typst:          {} ({})", typst, typst_bin);
+}
+
+// One ✅/❌ line per check, with a suggested fix for failures
+struct Checklist {
+    failures: usize,
+}
+
+impl Checklist {
+    fn check(&mut self, ok: bool, label: &str, fix: &str) {
+        if ok {
+            say!("✅ {}", label);
+        } else {
+            self.failures += 1;
+            say!("❌ {}", label);
+            say!("   💡 {}", fix);
+        }
+    }
+}
+
+fn run_doctor(data_dir: Option<&str>) {
+    say!("🩺 --- Doctor ---");
+    let mut list = Checklist { failures: 0 };
+
+    // Config file
+    let config_path = get_config_path();
+    let settings = load_settings();
+    let config_label = format!("Config file readable: {}", config_path.display());
+    let config_fix = if config_path.exists() {
+        "The file is not valid TOML; fix it or run `config` to rewrite it."
+    } else {
+        "Run `config` to create it."
+    };
+    list.check(settings.is_some(), &config_label, config_fix);
+    let settings = with_data_root_override(settings, data_dir);
+
+    if let Some(typst) = settings.as_ref().and_then(|s| s.typst_path.as_ref()) {
+        TYPST_BIN.set(expand_home_dir(typst)).ok();
+    }
+
+    // Typst
+    match typst_command().arg("--version").output() {
+        Ok(out) => list.check(true, &format!("Typst installed: {}", String::from_utf8_lossy(&out.stdout).trim()), ""),
+        Err(_) => list.check(false, "Typst installed",
+            "Install Typst (https://typst.app) or set typst_path via `config`; PDFs use the built-in layout until then."),
+    }
+
+    let Some(settings) = settings else {
+        say!("\n⚠️  Skipping data directory checks until the config file is fixed.");
+        return;
+    };
+
+    // Data root
+    let root = PathBuf::from(expand_home_dir(&settings.data_root));
+    let exists = root.is_dir();
+    list.check(exists, &format!("Data root exists: {}", root.display()), "Create the folder or point `config` at the right one.");
+    if !exists {
+        return;
+    }
+    let probe = root.join(".doctor-write-test");
+    let writable = fs::write(&probe, b"").is_ok();
+    fs::remove_file(&probe).ok();
+    list.check(writable, "Data root is writable", "Check the folder permissions (or whether it is a read-only mount).");
+
+    // Sender configs
+    let sender_path = root.join("sender.toml");
+    let profiles = sender_profiles(&root);
+    if profiles.is_empty() {
+        let result = if sender_path.exists() {
+            read_sender_file(&root, &sender_path, None).map(|_| ())
+        } else {
+            Err(anyhow::anyhow!("missing"))
+        };
+        let fix = match &result {
+            Err(e) if sender_path.exists() => format!("{:#}", e),
+            _ => "Run any command once to create a default sender.toml, then fill it in.".to_string(),
+        };
+        list.check(result.is_ok(), "sender.toml parses", &fix);
+    }
+    for name in &profiles {
+        let result = load_sender_profile(&root, name);
+        let fix = result.as_ref().err().map(|e| format!("{:#}", e)).unwrap_or_default();
+        list.check(result.is_ok(), &format!("senders/{}.toml parses", name), &fix);
+    }
+
+    // Templates (the default is created on the first invoice if missing)
+    let template_dir = root.join("templates");
+    let mut templates: Vec<PathBuf> = fs::read_dir(&template_dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.extension().is_some_and(|ext| ext == "tera")).collect())
+        .unwrap_or_default();
+    templates.sort();
+    if templates.is_empty() {
+        list.check(true, &format!("templates/{} will be created on the first invoice", DEFAULT_TEMPLATE_NAME), "");
+    }
+    for path in &templates {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| Tera::default().add_raw_template(&name, &content).map_err(|e| {
+                // The parser's position report is in the error source
+                std::error::Error::source(&e).map(|s| s.to_string()).unwrap_or_else(|| e.to_string())
+            }));
+        let fix = match &result {
+            Err(e) if name == DEFAULT_TEMPLATE_NAME => format!("Fix the Tera syntax, or delete the file to restore the default:{}", e),
+            Err(e) => format!("Fix the Tera syntax:{}", e),
+            Ok(_) => String::new(),
+        };
+        list.check(result.is_ok(), &format!("templates/{} parses", name), &fix);
+    }
+
+    if list.failures == 0 {
+        say!("\n✅ All checks passed.");
+    } else {
+        say!("\n⚠️  {} problem(s) found.", list.failures);
+    }
+}
+
+// Take an exclusive lock on `<root>/.lock`. The lock lives as long as the returned
+// file and is released by the OS when the process exits, including on Ctrl-C.
+fn acquire_data_lock(root: &Path) -> Option<fs::File> {
+    let path = root.join(".lock");
+    let mut file = match fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path) {
+        Ok(f) => f,
+        Err(e) => {
+            say_err!("⚠️  Could not open lock file {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    match file.try_lock() {
+        Ok(()) => {
+            // Record the owner for debugging stale locks
+            file.set_len(0).ok();
+            std::io::Write::write_all(&mut file, std::process::id().to_string().as_bytes()).ok();
+            Some(file)
+        }
+        Err(fs::TryLockError::WouldBlock) => {
+            say_err!("❌ Another instance is running (lock held on {:?}).", path);
+            std::process::exit(1);
+        }
+        Err(fs::TryLockError::Error(e)) => {
+            say_err!("⚠️  Could not lock data directory: {}", e);
+            None
+        }
+    }
+}
+
+fn expand_home_dir(path: &str) -> String {
+    if path.starts_with("~")
+        && let Some(base_dirs) = BaseDirs::new()
+    {
+        let home = base_dirs.home_dir().to_string_lossy();
+        return path.replacen("~", &home, 1);
+    }
+    path.to_string()
+}
+
+// Helper: Open file and reveal in Finder/Explorer
+fn open_and_reveal(path: &Path) {
+    if !AUTO_OPEN.load(std::sync::atomic::Ordering::Relaxed) {
+        say!("📄 {}", path.display());
+        return;
+    }
+
+    #[cfg(target_os = "macos")]
+    Command::new("open").arg("-R").arg(path).spawn().ok();
+
+    #[cfg(target_os = "windows")]
+    Command::new("explorer").arg(format!("/select,{}", path.to_string_lossy())).spawn().ok();
+    
+    #[cfg(target_os = "linux")]
+    Command::new("xdg-open").arg(path.parent().unwrap()).spawn().ok();
+
+    open_path(path);
+}
+
+// Open a file or folder with the default app
+fn open_path(path: &Path) {
+    #[cfg(target_os = "macos")]
+    Command::new("open").arg(path).spawn().ok();
+
+    #[cfg(target_os = "windows")]
+    Command::new("explorer").arg(path).spawn().ok();
+
+    #[cfg(target_os = "linux")]
+    Command::new("xdg-open").arg(path).spawn().ok();
+}
+
+// ==========================================
+// Safety Helpers (Destructive Operations)
+// ==========================================
+
+// Print a dry-run preview of a destructive operation, require an explicit
+// confirmation and optionally snapshot the affected files to `.trash/`.
+// Returns true when the caller may go ahead.
+fn confirm_destructive(root: &Path, preview: &[String], files: &[PathBuf]) -> bool {
+    say!("\n⚠️  The following changes will be made:");
+    for line in preview {
+        say!("   - {}", line);
+    }
+
+    let proceed = Confirm::new("Proceed with these changes?")
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+
+    if !proceed {
+        say!("⏸️  Operation cancelled.");
+        return false;
+    }
+
+    if files.is_empty() {
+        return true;
+    }
+
+    let backup = Confirm::new("Snapshot affected files to .trash/ first?")
+        .with_default(true)
+        .prompt()
+        .unwrap_or(true);
+
+    if backup {
+        match snapshot_to_trash(root, files) {
+            Ok(dir) => say!("🗑️  Snapshot saved to: {:?}", dir),
+            Err(e) => {
+                say_err!("❌ Failed to snapshot files, aborting: {}", e);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// Copy files (or directories) into `<root>/.trash/<timestamp>/`, keeping
+// their path relative to the data root so they can be restored by hand.
+fn snapshot_to_trash(root: &Path, files: &[PathBuf]) -> std::io::Result<PathBuf> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let trash_dir = root.join(".trash").join(stamp);
+    fs::create_dir_all(&trash_dir)?;
+
+    for file in files {
+        if !file.exists() { continue; }
+        let relative = match file.strip_prefix(root) {
+            Ok(rel) => rel.to_path_buf(),
+            Err(_) => PathBuf::from(file.file_name().unwrap_or_default()),
+        };
+        copy_recursive(file, &trash_dir.join(relative))?;
+    }
+    Ok(trash_dir)
+}
+
+fn copy_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dst)?;
+        for entry in fs::read_dir(src)?.flatten() {
+            copy_recursive(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+// ==========================================
+// 8. Summary Logic
+// ==========================================
+
+#[derive(Serialize, Deserialize, Clone)]
+struct InvoiceInfo {
+    id: String,
+    date: NaiveDate,
+    total: f64,
+    // Installments received so far (sidecar payments)
+    amount_paid: f64,
+    status: InvoiceStatus,
+    client: String,
+}
+
+// Date, total, status and client of one invoice (sidecar first, regex for legacy files)
+fn invoice_info(path: &Path) -> Option<InvoiceInfo> {
+    if let Some(record) = read_sidecar(path) {
+        let status = record.status();
+        let amount_paid = record.amount_paid();
+        return Some(InvoiceInfo { id: record.id, date: record.date, total: record.total, amount_paid, status, client: record.client_name });
+    }
+
+    // Any letter prefix: legacy files keep the prefix they were created with
+    let date_re = Regex::new(r"^[A-Za-z]+(\d{8})-\d+_").unwrap();
+    let filename = path.file_name()?.to_string_lossy().to_string();
+    let caps = date_re.captures(&filename)?;
+    let date = NaiveDate::parse_from_str(&caps[1], "%Y%m%d").ok()?;
+    let (total, client) = parse_invoice_total(path).ok()?;
+    let id = invoice_id_from_path(path)?;
+    Some(InvoiceInfo { id, date, total, amount_paid: 0.0, status: invoice_status(path), client })
+}
+
+// --- Summary Index (output/index.json) ---
+
+// Parsed invoice, reused until its .typ or sidecar is modified
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexEntry {
+    typ_modified: Option<std::time::SystemTime>,
+    sidecar_modified: Option<std::time::SystemTime>,
+    client_id: String,
+    is_estimate: bool,
+    info: InvoiceInfo,
+}
+
+// Keyed by path relative to output/
+#[derive(Serialize, Deserialize, Default)]
+struct InvoiceIndex {
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+const INDEX_FILE: &str = "index.json";
+
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn index_key(output_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(output_dir).unwrap_or(path).to_string_lossy().to_string()
+}
+
+fn index_entry(path: &Path) -> Option<IndexEntry> {
+    Some(IndexEntry {
+        typ_modified: modified_time(path),
+        sidecar_modified: sidecar_path(path).and_then(|p| modified_time(&p)),
+        client_id: invoice_client_id(path)?,
+        is_estimate: is_estimate(path),
+        info: invoice_info(path)?,
+    })
+}
+
+// A missing or unreadable index is simply rebuilt
+fn load_index(output_dir: &Path) -> InvoiceIndex {
+    fs::read_to_string(output_dir.join(INDEX_FILE)).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+// Summaries refresh the index without the data lock, so it is replaced atomically
+fn save_index(output_dir: &Path, index: &InvoiceIndex) {
+    if let Ok(json) = serde_json::to_string(index) {
+        write_atomic(&output_dir.join(INDEX_FILE), json.as_bytes()).ok();
+    }
+}
+
+// Write to a per-process temp file next to `path`, then rename it over `path`:
+// concurrent readers see either the old or the new file, never a partial one
+fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        fs::remove_file(&tmp).ok();
+    })
+}
+
+// Refresh one invoice's entry after it was (re)rendered
+fn update_index(output_dir: &Path, typ_path: &Path) {
+    let mut index = load_index(output_dir);
+    let key = index_key(output_dir, typ_path);
+    match index_entry(typ_path) {
+        Some(entry) => index.entries.insert(key, entry),
+        None => index.entries.remove(&key),
+    };
+    save_index(output_dir, &index);
+}
+
+// Entries for `paths`, parsing only files that are new or modified since they were indexed.
+// The index is rewritten when anything changed; entries of deleted files are dropped.
+fn indexed_invoices(output_dir: &Path, paths: &[PathBuf]) -> Vec<IndexEntry> {
+    let mut index = load_index(output_dir);
+    let mut changed = false;
+    let mut entries = Vec::new();
+
+    for path in paths {
+        let key = index_key(output_dir, path);
+        let fresh = index.entries.get(&key).is_some_and(|e| {
+            e.typ_modified.is_some()
+                && e.typ_modified == modified_time(path)
+                && e.sidecar_modified == sidecar_path(path).and_then(|p| modified_time(&p))
+        });
+        if !fresh {
+            changed = true;
+            match index_entry(path) {
+                Some(entry) => index.entries.insert(key.clone(), entry),
+                None => index.entries.remove(&key),
+            };
+        }
+        if let Some(entry) = index.entries.get(&key) {
+            entries.push(entry.clone());
+        }
+    }
+
+    let before = index.entries.len();
+    index.entries.retain(|key, _| output_dir.join(key).exists());
+    if changed || index.entries.len() != before {
+        save_index(output_dir, &index);
+    }
+    entries
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SummaryFormat {
+    Table,
+    Markdown,
+    Json,
+}
+
+// `summary --json` output
+#[derive(Serialize)]
+struct SummaryJson {
+    year: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client: Option<String>,
+    monthly: Vec<MonthJson>,
+    clients: Vec<ClientJson>,
+}
+
+#[derive(Serialize)]
+struct MonthJson {
+    month: String, // "2024-03"
+    #[serde(flatten)]
+    totals: TotalsJson,
+}
+
+#[derive(Serialize)]
+struct ClientJson {
+    client: String,
+    #[serde(flatten)]
+    totals: TotalsJson,
+}
+
+#[derive(Serialize)]
+struct TotalsJson {
+    collected: f64,
+    partially_paid: f64,
+    outstanding: f64,
+    total: f64,
+    invoices: usize,
+    average: f64,
+}
+
+impl TotalsJson {
+    fn new(totals: &Totals) -> TotalsJson {
+        let round = |v: f64| (v * 100.0).round() / 100.0;
+        TotalsJson {
+            collected: round(totals.collected),
+            partially_paid: round(totals.partial),
+            outstanding: round(totals.outstanding),
+            total: round(totals.total()),
+            invoices: totals.count,
+            average: round(totals.average()),
+        }
+    }
+}
+
+// Invoiced amounts split by payment progress:
+// Collected = fully paid invoices, Partially Paid = installments on open invoices,
+// Outstanding = what is still owed
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    collected: f64,
+    partial: f64,
+    outstanding: f64,
+    // Number of invoices behind these amounts
+    count: usize,
+}
+
+impl Totals {
+    fn add(&mut self, info: &InvoiceInfo) {
+        self.count += 1;
+        if info.status.is_paid() {
+            self.collected += info.total;
+        } else {
+            self.partial += info.amount_paid;
+            self.outstanding += info.total - info.amount_paid;
+        }
+    }
+
+    fn add_totals(&mut self, other: &Totals) {
+        self.collected += other.collected;
+        self.partial += other.partial;
+        self.outstanding += other.outstanding;
+        self.count += other.count;
+    }
+
+    fn total(&self) -> f64 {
+        self.collected + self.partial + self.outstanding
+    }
+
+    // Average invoice amount; 0 when there are no invoices
+    fn average(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.total() / self.count as f64 }
+    }
+}
+
+fn show_summary(root: &Path, args: &SummaryArgs, sender: &SenderConfig) {
+    let format = if args.json { SummaryFormat::Json } else if args.markdown { SummaryFormat::Markdown } else { SummaryFormat::Table };
+    let (year, compare, client, csv) = (args.year, args.compare, args.client.as_deref(), args.csv.as_deref());
+    let markdown = format == SummaryFormat::Markdown;
+    let json = format == SummaryFormat::Json;
+    let output_dir = root.join("output");
+    if !output_dir.exists() && !json {
+        say!("❌ No output directory found. No invoices to summarize.");
+        return;
+    }
+
+    let target_year = year.unwrap_or_else(|| Local::now().year());
+    if format == SummaryFormat::Table {
+        match client {
+            Some(id) => say!("🔍 Scanning invoices for summary (Year: {}, Client: {})...", target_year, id),
+            None => say!("🔍 Scanning invoices for summary (Year: {})...", target_year),
+        }
+    }
+
+    // 1. Recursively find all .typ files, whatever the output layout
+    let typ_files = collect_files(&output_dir, "typ");
+
+    // 2. Date, total and status per file from output/index.json, re-parsing only changed files
+    // (VOID invoices and estimates are excluded from summary)
+    let mut entries = indexed_invoices(&output_dir, &typ_files);
+    if args.archived {
+        entries.extend(archived_invoices(&output_dir));
+    }
+    let invoice_infos: Vec<InvoiceInfo> = entries
+        .into_iter()
+        .filter(|e| !e.info.status.is_void() && !e.is_estimate)
+        .filter(|e| client.is_none_or(|id| e.client_id == id))
+        .map(|e| e.info)
+        .collect();
+
+    if invoice_infos.is_empty() && !json {
+        say!("No invoices found.");
+        return;
+    }
+
+    // 3. Group by month and calculate totals
+    let monthly_totals = aggregate_monthly(&invoice_infos, target_year);
+    let prev_year = target_year - 1;
+    let prev_monthly = if compare { Some(aggregate_monthly(&invoice_infos, prev_year)) } else { None };
+
+    let mut client_totals: BTreeMap<String, Totals> = BTreeMap::new();
+    for info in invoice_infos.iter().filter(|i| i.date.year() == target_year) {
+        client_totals.entry(info.client.clone()).or_default().add(info);
+    }
+
+    if let Some(csv_path) = csv {
+        match write_summary_csv(csv_path, target_year, &monthly_totals, &client_totals) {
+            Ok(_) if json => {}
+            Ok(_) => say!("✅ CSV written: {:?}", csv_path),
+            Err(e) => say_err!("❌ Failed to write CSV: {}", e),
+        }
+    }
+
+    if json {
+        let summary = SummaryJson {
+            year: target_year,
+            client: client.map(str::to_string),
+            monthly: monthly_totals.iter()
+                .map(|(month, t)| MonthJson { month: format!("{}-{:02}", target_year, month), totals: TotalsJson::new(t) })
+                .collect(),
+            clients: client_totals.iter()
+                .map(|(client, t)| ClientJson { client: client.clone(), totals: TotalsJson::new(t) })
+                .collect(),
+        };
+        say!("{}", serde_json::to_string_pretty(&summary).unwrap());
+        return;
+    }
+
+    // 4. Create table using comfy-table (Monthly, or Q1-Q4 with --quarterly)
+    let mut table = summary_table(markdown);
+    let mut header = vec![
+        Cell::new(if args.quarterly { "Quarter" } else { "Month" }),
+        Cell::new("Collected"),
+        Cell::new("Partially Paid"),
+        Cell::new("Outstanding"),
+        Cell::new("Total"),
+        Cell::new("Invoices"),
+        Cell::new("Average"),
+    ];
+    if compare {
+        header.push(Cell::new(format!("Total ({})", prev_year)));
+        header.push(Cell::new("Change"));
+    }
+    table.set_header(header);
+
+    // Rows as (label, totals, previous year's total)
+    let mut rows: Vec<(String, Totals, Option<f64>)> = Vec::new();
+    if args.quarterly {
+        // Always four rows, Q1 first
+        let quarterly = aggregate_quarterly(&monthly_totals, target_year);
+        let prev_quarterly = prev_monthly.as_ref().map(|prev| aggregate_quarterly(prev, prev_year));
+        for quarter in 1..=4 {
+            let totals = quarterly.get(&(target_year, quarter)).copied().unwrap_or_default();
+            let prev_total = prev_quarterly.as_ref().map(|prev| prev.get(&(prev_year, quarter)).map(|t| t.total()).unwrap_or(0.0));
+            rows.push((format!("Q{} {}", quarter, target_year), totals, prev_total));
+        }
+    } else {
+        // Months present in either year are shown when comparing
+        let mut months: Vec<u32> = monthly_totals.keys().copied().collect();
+        if let Some(prev) = &prev_monthly {
+            months.extend(prev.keys().copied());
+            months.sort();
+            months.dedup();
+        }
+        for month in months.iter().rev() {
+            let totals = monthly_totals.get(month).copied().unwrap_or_default();
+            let month_str = NaiveDate::from_ymd_opt(target_year, *month, 1).unwrap().format("%B %Y").to_string();
+            let prev_total = prev_monthly.as_ref().map(|prev| prev.get(month).map(|t| t.total()).unwrap_or(0.0));
+            rows.push((month_str, totals, prev_total));
+        }
+    }
+
+    let mut year_totals = Totals::default();
+    let mut total_prev = 0.0;
+
+    for (label, totals, prev_total) in rows {
+        let mut row = vec![Cell::new(label)];
+        row.extend(totals_cells(&totals, sender));
+        if let Some(prev_total) = prev_total {
+            row.push(Cell::new(sender.format_money(prev_total)));
+            row.push(change_cell(totals.total(), prev_total, sender));
+            total_prev += prev_total;
+        }
+        table.add_row(row);
+        year_totals.add_totals(&totals);
+    }
+
+    let mut total_row = vec![Cell::new(format!("Total ({})", target_year))];
+    total_row.extend(totals_cells(&year_totals, sender));
+    if compare {
+        total_row.push(Cell::new(sender.format_money(total_prev)));
+        total_row.push(change_cell(year_totals.total(), total_prev, sender));
+    }
+    table.add_row(total_row.into_iter().map(|c| c.add_attribute(Attribute::Bold)).collect::<Vec<_>>());
+
+    let period = if args.quarterly { "Quarterly" } else { "Monthly" };
+    let for_client = client.map(|id| format!(" - {}", id)).unwrap_or_default();
+    if compare {
+        print_summary_heading(&format!("{} Invoice Summary ({} vs {}){}", period, target_year, prev_year, for_client), markdown);
+    } else {
+        print_summary_heading(&format!("{} Invoice Summary ({}){}", period, target_year, for_client), markdown);
+    }
+    say!("{table}");
+
+    // 5. Client Summary Table
+    let mut client_table = summary_table(markdown);
+    client_table.set_header(vec![
+        Cell::new("Client"),
+        Cell::new("Collected"),
+        Cell::new("Partially Paid"),
+        Cell::new("Outstanding"),
+        Cell::new("Total"),
+        Cell::new("Invoices"),
+        Cell::new("Average"),
+    ]);
+
+    // Sort clients by total amount descending
+    let mut client_vec: Vec<_> = client_totals.into_iter().collect();
+    client_vec.sort_by(|a, b| b.1.total().partial_cmp(&a.1.total()).unwrap());
+
+    for (client, totals) in client_vec {
+        let mut row = vec![Cell::new(client)];
+        row.extend(totals_cells(&totals, sender));
+        client_table.add_row(row);
+    }
+
+    print_summary_heading(&format!("Client Summary ({})", target_year), markdown);
+    say!("{client_table}");
+}
+
+
+// Summary tables render as GitHub-flavored Markdown (no colors) when requested
+fn summary_table(markdown: bool) -> Table {
+    let mut table = new_table();
+    if markdown {
+        table.load_preset(comfy_table::presets::ASCII_MARKDOWN);
+        table.force_no_tty();
+    }
+    table
+}
+
+fn print_summary_heading(title: &str, markdown: bool) {
+    if markdown {
+        say!("\n## {}\n", title);
+    } else {
+        say!("\n--- {} ---", title);
+    }
+}
+
+// Monthly block, a blank line, then the client block. Amounts are plain numbers for spreadsheets.
+fn write_summary_csv(path: &Path, year: i32, monthly: &BTreeMap<u32, Totals>, clients: &BTreeMap<String, Totals>) -> std::io::Result<()> {
+    let columns = ["Collected", "Partially Paid", "Outstanding", "Total", "Invoices", "Average"];
+    let row = |label: &str, t: &Totals| {
+        let amounts = [t.collected, t.partial, t.outstanding, t.total()].map(|a| format!("{:.2}", a));
+        format!("{},{},{},{:.2}\n", csv_field(label), amounts.join(","), t.count, t.average())
+    };
+
+    let mut out = format!("Month,{}\n", columns.join(","));
+    for (month, totals) in monthly {
+        let month_str = NaiveDate::from_ymd_opt(year, *month, 1).unwrap().format("%Y-%m").to_string();
+        out.push_str(&row(&month_str, totals));
+    }
+
+    out.push_str(&format!("\nClient,{}\n", columns.join(",")));
+    for (client, totals) in clients {
+        out.push_str(&row(client, totals));
+    }
+
+    fs::write(path, out)
+}
+
+// Quote a CSV field when it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Monthly totals for a single year, keyed by month number
+fn aggregate_monthly(infos: &[InvoiceInfo], year: i32) -> BTreeMap<u32, Totals> {
+    let mut monthly: BTreeMap<u32, Totals> = BTreeMap::new();
+    for info in infos.iter().filter(|i| i.date.year() == year) {
+        monthly.entry(info.date.month()).or_default().add(info);
+    }
+    monthly
+}
+
+// Re-bucket one year's monthly totals into quarters, keyed by (year, quarter)
+fn aggregate_quarterly(monthly: &BTreeMap<u32, Totals>, year: i32) -> BTreeMap<(i32, u32), Totals> {
+    let mut quarterly: BTreeMap<(i32, u32), Totals> = BTreeMap::new();
+    for (month, totals) in monthly {
+        quarterly.entry((year, (month - 1) / 3 + 1)).or_default().add_totals(totals);
+    }
+    quarterly
+}
+
+// Collected (green) / Partially Paid (yellow) / Outstanding (red) / Total / Invoices / Average cells
+fn totals_cells(totals: &Totals, sender: &SenderConfig) -> Vec<Cell> {
+    let colored = |amount: f64, color: Color| {
+        let cell = Cell::new(sender.format_money(amount));
+        if amount > 0.0 { cell.fg(color) } else { cell }
+    };
+    vec![
+        colored(totals.collected, Color::Green),
+        colored(totals.partial, Color::Yellow),
+        colored(totals.outstanding, Color::Red),
+        Cell::new(sender.format_money(totals.total())),
+        Cell::new(totals.count),
+        Cell::new(sender.format_money(totals.average())),
+    ]
+}
+
+// Year-over-year change cell: "+$120.00 (+15.0%)", green for growth, red for decline
+fn change_cell(current: f64, previous: f64, sender: &SenderConfig) -> Cell {
+    let diff = current - previous;
+    let sign = if diff < 0.0 { "-" } else { "+" };
+    let text = if previous > 0.0 {
+        format!("{}{} ({:+.1}%)", sign, sender.format_money(diff.abs()), diff / previous * 100.0)
+    } else if current > 0.0 {
+        format!("{}{} (new)", sign, sender.format_money(diff.abs()))
+    } else {
+        "-".to_string()
+    };
+
+    let cell = Cell::new(text);
+    if diff > 0.0 {
+        cell.fg(Color::Green)
+    } else if diff < 0.0 {
+        cell.fg(Color::Red)
+    } else {
+        cell
+    }
+}
+
+fn parse_invoice_total(path: &Path) -> Result<(f64, String), std::io::Error> {
+    let amounts = parse_invoice_amounts(path)?;
+    Ok((amounts.total, amounts.client_name))
+}
+
+// Amounts printed on a .typ without a sidecar
+struct ParsedAmounts {
+    tax_rate: f64,
+    tax_amount: f64,
+    total: f64,
+    client_name: String,
+}
+
+// Newer files carry the computed subtotal/tax_amount/total; older ones are re-derived
+// from the item amounts and the tax rate
+fn parse_invoice_amounts(path: &Path) -> Result<ParsedAmounts, std::io::Error> {
+    let content = fs::read_to_string(path)?;
+
+    // Use global search for amount and tax_rate, which is more robust
+    let amount_re = Regex::new(r#"\bamount:\s*(-?[\d\.]+)"#).unwrap();
+    let tax_re = Regex::new(r"tax_rate:\s*([\d\.]+)").unwrap();
+    let client_re = Regex::new(r#"client:\s*\(\s*name:\s*"([^"]+)""#).unwrap();
+    // Numeric values only: the template's own defaults are `none`
+    let stored = |field: &str| {
+        let re = Regex::new(&format!(r"\b{}:\s*(-?[\d\.]+)", field)).unwrap();
+        last_capture(&re, &content).and_then(|v| v.parse::<f64>().ok())
+    };
+
+    // Get tax_rate (last match: the template's own `tax_rate: 0.0` default comes first)
+    let tax_rate = last_capture(&tax_re, &content)
+        .and_then(|r| r.parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    let subtotal = stored("subtotal").unwrap_or_else(|| {
+        // Sum all amounts found in the file
+        amount_re.captures_iter(&content).filter_map(|cap| cap[1].parse::<f64>().ok()).sum()
+    });
+    let tax_amount = stored("tax_amount").unwrap_or(subtotal * tax_rate);
+    let total = stored("total").unwrap_or(subtotal + tax_amount);
+
+    // Get client name
+    let client_name = if let Some(client_cap) = client_re.captures(&content) {
+        client_cap[1].replace("Attn:", "").trim().to_string()
+    } else {
+        "Unknown Client".to_string()
+    };
+
+    Ok(ParsedAmounts { tax_rate, tax_amount, total, client_name })
+}
+
+// --- Tax Report ---
+
+// Tax label printed on a .typ (last match: the template's own `tax_label: "Tax"` default
+// comes first); None for invoices rendered before the label was configurable
+fn printed_tax_label(content: &str) -> Option<String> {
+    let label_re = Regex::new(&format!(r"\btax_label:\s*{}", TYP_STR)).unwrap();
+    last_capture(&label_re, content).map(|l| typst_unescape_str(&l))
+}
+
+// Tax charged on one invoice, one line per rate, under the label it was printed with
+fn invoice_taxes(path: &Path, sender: &SenderConfig) -> Option<(NaiveDate, Vec<TaxLine>)> {
+    let label = fs::read_to_string(path).ok()
+        .and_then(|content| printed_tax_label(&content))
+        .unwrap_or_else(|| sender.tax_label.clone());
+    if let Some(record) = read_sidecar(path) {
+        // Stacked taxes share the same base, so the stored sum splits by rate
+        let mut taxes = vec![TaxLine { label: label.clone(), rate: record.tax_rate, amount: record.tax_amount }];
+        if let Some(second) = &record.second_tax {
+            let combined = record.tax_rate + second.rate;
+            if combined > 0.0 {
+                taxes = vec![
+                    TaxLine { label, rate: record.tax_rate, amount: record.tax_amount * record.tax_rate / combined },
+                    TaxLine { label: second.label.clone(), rate: second.rate, amount: record.tax_amount * second.rate / combined },
+                ];
+            }
+        }
+        return Some((record.date, taxes));
+    }
+
+    let info = invoice_info(path)?;
+    let amounts = parse_invoice_amounts(path).ok()?;
+    Some((info.date, vec![TaxLine { label, rate: amounts.tax_rate, amount: amounts.tax_amount }]))
+}
+
+fn rate_label(rate: f64) -> String {
+    format!("{}%", (rate * 100.0 * 1000.0).round() / 1000.0)
+}
+
+fn show_tax_report(root: &Path, args: &TaxReportArgs, sender: &SenderConfig) {
+    let output_dir = root.join("output");
+    if !output_dir.exists() {
+        say!("❌ No output directory found. No invoices to report.");
+        return;
+    }
+
+    let target_year = args.year.unwrap_or_else(|| Local::now().year());
+    say!("🔍 Scanning invoices for tax collected (Year: {})...", target_year);
+
+    // VOID invoices and estimates never collected tax; credit notes reduce it
+    let mut monthly: BTreeMap<u32, f64> = BTreeMap::new();
+    // Keyed by label and rate in thousandths of a percent so equal rates group together
+    let mut by_rate: BTreeMap<(String, i64), (f64, f64)> = BTreeMap::new();
+    for path in collect_files(&output_dir, "typ") {
+        if invoice_status(&path).is_void() || is_estimate(&path) {
+            continue;
+        }
+        let Some((date, taxes)) = invoice_taxes(&path, sender) else { continue };
+        if date.year() != target_year {
+            continue;
+        }
+        for tax in taxes {
+            if tax.rate <= 0.0 || tax.amount == 0.0 {
+                continue;
+            }
+            *monthly.entry(date.month()).or_default() += tax.amount;
+            let entry = by_rate.entry((tax.label, (tax.rate * 100_000.0).round() as i64)).or_insert((tax.rate, 0.0));
+            entry.1 += tax.amount;
+        }
+    }
+
+    if monthly.is_empty() {
+        say!("No tax collected in {}.", target_year);
+        return;
+    }
+
+    let mut month_table = new_table();
+    month_table.set_header(vec![Cell::new("Month"), Cell::new("Tax Collected")]);
+    let mut year_total = 0.0;
+    for (month, amount) in &monthly {
+        year_total += amount;
+        let month_str = NaiveDate::from_ymd_opt(target_year, *month, 1).unwrap().format("%B %Y").to_string();
+        month_table.add_row(vec![Cell::new(month_str), Cell::new(sender.format_money(*amount))]);
+    }
+    month_table.add_row(vec![
+        Cell::new(format!("Total {}", target_year)).add_attribute(Attribute::Bold),
+        Cell::new(sender.format_money(year_total)).add_attribute(Attribute::Bold),
+    ]);
+
+    let mut rate_table = new_table();
+    rate_table.set_header(vec![Cell::new("Tax"), Cell::new("Rate"), Cell::new("Taxable Sales"), Cell::new("Tax Collected")]);
+    for ((label, _), (rate, amount)) in &by_rate {
+        rate_table.add_row(vec![
+            Cell::new(label),
+            Cell::new(rate_label(*rate)),
+            Cell::new(sender.format_money(amount / rate)),
+            Cell::new(sender.format_money(*amount)),
+        ]);
+    }
+
+    say!("\n--- Tax Collected by Month ({}) ---", target_year);
+    say!("{month_table}");
+    say!("\n--- Tax Collected by Rate ({}) ---", target_year);
+    say!("{rate_table}");
+
+    if let Some(csv_path) = &args.csv {
+        match write_tax_csv(csv_path, target_year, &monthly, &by_rate) {
+            Ok(_) => say!("✅ CSV written: {:?}", csv_path),
+            Err(e) => say_err!("❌ Failed to write CSV: {}", e),
+        }
+    }
+}
+
+// Monthly block, a blank line, then the per-rate block. Amounts are plain numbers for spreadsheets.
+fn write_tax_csv(path: &Path, year: i32, monthly: &BTreeMap<u32, f64>, by_rate: &BTreeMap<(String, i64), (f64, f64)>) -> std::io::Result<()> {
+    let mut out = String::from("Month,Tax Collected\n");
+    for (month, amount) in monthly {
+        let month_str = NaiveDate::from_ymd_opt(year, *month, 1).unwrap().format("%Y-%m").to_string();
+        out.push_str(&format!("{},{:.2}\n", month_str, amount));
+    }
+
+    out.push_str("\nTax,Rate,Taxable Sales,Tax Collected\n");
+    for ((label, _), (rate, amount)) in by_rate {
+        out.push_str(&format!("{},{},{:.2},{:.2}\n", csv_field(label), rate_label(*rate), amount / rate, amount));
+    }
+
+    fs::write(path, out)
+}
+
+// ==========================================
+// 9. Recurring Invoices
+// ==========================================
+
+fn run_recurring(root: &Path, data_dir: &Path, sender: &SenderConfig, invoice_prefix: &str) -> anyhow::Result<()> {
+    let today = Local::now().date_naive();
+
+    // (client_id, recurrence index) for every recurrence due this month
+    let mut client_ids: Vec<String> = fs::read_dir(data_dir)
+        .map(|entries| entries.filter_map(|e| e.ok())
+            .filter(|e| e.path().join("info.toml").exists())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect())
+        .unwrap_or_default();
+    client_ids.sort();
+
+    let mut due: Vec<(String, usize)> = Vec::new();
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("Client"),
+        Cell::new("Recurrence"),
+        Cell::new("Project"),
+        Cell::new("Cadence"),
+        Cell::new("Last Generated"),
+        Cell::new("Amount"),
+    ]);
+
+    for client_id in &client_ids {
+        let config = load_client_config(data_dir, client_id)?;
+        for (i, recurring) in config.recurring.iter().enumerate() {
+            if !recurring.is_due(today) {
+                continue;
+            }
+            due.push((client_id.clone(), i));
+            table.add_row(vec![
+                Cell::new(client_id),
+                Cell::new(&recurring.id),
+                Cell::new(&recurring.project_id),
+                Cell::new(format!("{:?}", recurring.cadence)),
+                Cell::new(recurring.last_generated.map(|d| d.format("%m/%d/%Y").to_string()).unwrap_or_else(|| "Never".to_string())),
+                Cell::new(sender.format_money(recurring.items.iter().map(|i| i.amount).sum())),
+            ]);
+        }
+    }
+
+    if due.is_empty() {
+        say!("✅ No recurring invoices due this month.");
+        return Ok(());
+    }
+
+    say!("\n--- Recurring Invoices Due ({}) ---", today.format("%B %Y"));
+    say!("{table}");
+
+    let generate = Confirm::new(&format!("Generate {} invoice(s) dated today?", due.len()))
+        .with_default(true)
+        .prompt()?;
+    if !generate {
+        return Ok(());
+    }
+
+    let mut generated = 0;
+    for (client_id, index) in due {
+        let mut config = load_client_config(data_dir, &client_id)?;
+        let recurring = config.recurring[index].clone();
+        let Some(project) = config.projects.iter().find(|p| p.id == recurring.project_id).cloned() else {
+            say!("❌ Project '{}' not found for client '{}', skipping '{}'.", recurring.project_id, client_id, recurring.id);
+            continue;
+        };
+
+        let input = recurring_input(&recurring, today, sender.tax_inclusive);
+        let Some(pdf_path) = write_invoice(root, &client_id, &config, &project, &input, sender, invoice_prefix)? else { continue };
+        copy_path_to_clipboard(&pdf_path);
+
+        config.recurring[index].last_generated = Some(today);
+        save_client_config(data_dir, &client_id, &config)?;
+        generated += 1;
+    }
+
+    say!("✅ Generated {} recurring invoice(s).", generated);
+    Ok(())
+}
+
+fn recurring_input(recurring: &RecurringInvoice, date: NaiveDate, tax_inclusive: bool) -> InvoiceInput {
+    let tax = TaxChoice::from_percent(recurring.tax_rate.unwrap_or(0.0), tax_inclusive);
+    let (terms, due_date) = net_terms(date, recurring.terms_days.unwrap_or(DEFAULT_TERM_DAYS));
+
+    InvoiceInput {
+        items: recurring.items.clone(),
+        date,
+        tax_rate: tax.rate,
+        tax_status: tax.status,
+        second_tax: None,
+        tax_inclusive: tax.inclusive,
+        discount: None,
+        terms,
+        due_date: Some(due_date),
+        copy_labels: vec![],
+        is_estimate: false,
+        credit_for: None,
+        notes: None,
+        template: None,
+    }
+}
+
+// ==========================================
+// 10. Overdue & Aging Reports
+// ==========================================
+
+// An UNPAID invoice with its remaining balance
+struct OpenInvoice {
+    id: String,
+    client: String,
+    date: NaiveDate,
+    due_date: Option<NaiveDate>,
+    balance: f64,
+}
+
+// UNPAID invoices under output/ (PAID, VOID and estimates are skipped)
+fn open_invoices(output_dir: &Path, date_format: &str) -> Vec<OpenInvoice> {
+    let due_re = Regex::new(r#"due_date:\s*"([^"]+)""#).unwrap();
+    collect_files(output_dir, "typ")
+        .into_iter()
+        .filter(|p| invoice_status(p) == InvoiceStatus::Unpaid && !is_estimate(p))
+        .filter_map(|path| {
+            let info = invoice_info(&path)?;
+            // Sidecar first; legacy files need a parsed due_date field
+            let due_date = match read_sidecar(&path) {
+                Some(record) => record.due_date,
+                None => fs::read_to_string(&path).ok()
+                    .and_then(|content| last_capture(&due_re, &content))
+                    .and_then(|d| parse_printed_date(&d, date_format)),
+            };
+            let balance = ((info.total - info.amount_paid) * 100.0).round() / 100.0;
+            Some(OpenInvoice { id: info.id, client: info.client, date: info.date, due_date, balance })
+        })
+        .collect()
+}
+
+struct OverdueInfo {
+    invoice_id: String,
+    client: String,
+    due_date: NaiveDate,
+    days_overdue: i64,
+    total: f64,
+}
+
+fn show_overdue(root: &Path, sender: &SenderConfig) {
+    let output_dir = root.join("output");
+    if !output_dir.exists() {
+        say!("❌ No output directory found.");
+        return;
+    }
+
+    let today = Local::now().date_naive();
+    let mut overdue: Vec<OverdueInfo> = Vec::new();
+
+    for open in open_invoices(&output_dir, &sender.date_format) {
+        let Some(due_date) = open.due_date else { continue };
+        let days_overdue = (today - due_date).num_days();
+        if days_overdue > 0 {
+            overdue.push(OverdueInfo { invoice_id: open.id, client: open.client, due_date, days_overdue, total: open.balance });
+        }
+    }
+
+    if overdue.is_empty() {
+        say!("✅ No overdue invoices.");
+        return;
+    }
+
+    overdue.sort_by_key(|o| std::cmp::Reverse(o.days_overdue));
+
+    let mut table = new_table();
+    table.set_header(vec![
+        Cell::new("Invoice"),
+        Cell::new("Client"),
+        Cell::new("Due Date"),
+        Cell::new("Days Overdue"),
+        Cell::new("Amount"),
+    ]);
+
+    let mut total_overdue = 0.0;
+    for info in &overdue {
+        total_overdue += info.total;
+        table.add_row(vec![
+            Cell::new(&info.invoice_id),
+            Cell::new(&info.client),
+            Cell::new(info.due_date.format("%m/%d/%Y").to_string()),
+            Cell::new(info.days_overdue),
+            Cell::new(sender.format_money(info.total)).fg(Color::Red),
+        ]);
+    }
+
+    table.add_row(vec![
+        Cell::new(format!("Total ({})", overdue.len())).add_attribute(Attribute::Bold),
+        Cell::new(""),
+        Cell::new(""),
+        Cell::new(""),
+        Cell::new(sender.format_money(total_overdue)).add_attribute(Attribute::Bold).fg(Color::Red),
+    ]);
+
+    say!("\n--- Overdue Invoices (as of {}) ---", today.format("%m/%d/%Y"));
+    say!("{table}");
+}
+
+// Receivables aging: balance by days past due (issue date when there is no due date)
+const AGING_BUCKETS: [&str; 5] = ["Current", "1-30", "31-60", "61-90", "90+"];
+
+fn aging_bucket(days_past_due: i64) -> usize {
+    match days_past_due {
+        i64::MIN..=0 => 0,
+        1..=30 => 1,
+        31..=60 => 2,
+        61..=90 => 3,
+        _ => 4,
+    }
+}
+
+fn show_aging(root: &Path, sender: &SenderConfig) {
+    let output_dir = root.join("output");
+    if !output_dir.exists() {
+        say!("❌ No output directory found.");
+        return;
+    }
+
+    let today = Local::now().date_naive();
+    let mut clients: BTreeMap<String, [f64; 5]> = BTreeMap::new();
+    for open in open_invoices(&output_dir, &sender.date_format) {
+        let days = (today - open.due_date.unwrap_or(open.date)).num_days();
+        clients.entry(open.client).or_default()[aging_bucket(days)] += open.balance;
+    }
+
+    if clients.is_empty() {
+        say!("✅ No unpaid invoices.");
+        return;
+    }
+
+    let mut table = new_table();
+    let mut header = vec![Cell::new("Client")];
+    header.extend(AGING_BUCKETS.iter().map(Cell::new));
+    header.push(Cell::new("Total"));
+    table.set_header(header);
+
+    let mut totals = [0.0; 5];
+    for (client, buckets) in &clients {
+        let mut row = vec![Cell::new(client)];
+        for (i, amount) in buckets.iter().enumerate() {
+            totals[i] += amount;
+            row.push(aging_cell(*amount, i, sender));
+        }
+        row.push(Cell::new(sender.format_money(buckets.iter().sum())));
+        table.add_row(row);
+    }
+
+    let mut total_row = vec![Cell::new("Total").add_attribute(Attribute::Bold)];
+    for (i, amount) in totals.iter().enumerate() {
+        total_row.push(aging_cell(*amount, i, sender).add_attribute(Attribute::Bold));
+    }
+    total_row.push(Cell::new(sender.format_money(totals.iter().sum())).add_attribute(Attribute::Bold));
+    table.add_row(total_row);
+
+    say!("\n--- Receivables Aging (as of {}) ---", today.format("%m/%d/%Y"));
+    say!("{table}");
 }
 
-const DEFAULT_SENDER_TEMPLATE: &str = include_str!("../sender.toml");
-
-fn load_sender_config(root: &Path) -> SenderConfig {
-    let path = root.join("sender.toml");
-    if path.exists() {
-        let content = fs::read_to_string(&path).expect("Failed to read sender.toml");
-        toml::from_str(&content).expect("Failed to parse sender.toml")
+// Empty buckets stay blank; anything past due is red
+fn aging_cell(amount: f64, bucket: usize, sender: &SenderConfig) -> Cell {
+    if amount == 0.0 {
+        Cell::new("")
+    } else if bucket == 0 {
+        Cell::new(sender.format_money(amount))
     } else {
-        println!("✨ Initializing default sender configuration...");
-        let default_sender: SenderConfig = toml::from_str(DEFAULT_SENDER_TEMPLATE).expect("Failed to parse default sender.toml");
-        fs::write(&path, DEFAULT_SENDER_TEMPLATE).expect("Failed to write sender.toml");
-        default_sender
+        Cell::new(sender.format_money(amount)).fg(Color::Red)
     }
 }
 
-fn setup_config_wizard() -> AppSettings {
-    println!("\n⚙️  --- Configuration Setup ---");
-    let current = load_settings();
-    let default_val = current.map(|s| s.data_root).unwrap_or_else(|| "~/Documents/Business".to_string());
-
-    println!("📂 Opening folder picker...");
-    let picked_path = rfd::FileDialog::new()
-        .set_title("Select Root Data Directory")
-        .pick_folder();
+// ==========================================
+// 11. Email Sending
+// ==========================================
 
-    let new_root = if let Some(path) = picked_path {
-        path.to_string_lossy().to_string()
-    } else {
-        println!("❌ No folder selected. Falling back to manual input.");
-        Text::new("Enter Root Data Directory:").with_default(&default_val).prompt().unwrap()
+fn send_invoice(root: &Path, data_dir: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let Some(typ_path) = select_invoice(root, &[InvoiceStatus::Unpaid], "Select Invoice to Send:") else { return Ok(()) };
+    let sender = &invoice_sender(root, &typ_path, sender);
+    let Some(smtp) = &sender.smtp else {
+        say!("❌ No [smtp] section in the sender config. Add host, port, username and from to enable sending.");
+        return Ok(());
     };
+    let pdf_path = typ_path.with_extension("pdf");
+    if !pdf_path.exists() {
+        say!("❌ PDF not found: {:?}", pdf_path);
+        return Ok(());
+    }
 
-    let settings = AppSettings { data_root: new_root };
-    
-    let path = get_config_path();
-    let toml_str = toml::to_string_pretty(&settings).unwrap();
-    fs::write(&path, toml_str).expect("Failed to save settings");
-    println!("✅ Settings saved.");
-    settings
-}
-
-fn expand_home_dir(path: &str) -> String {
-    if path.starts_with("~") {
-        if let Some(base_dirs) = BaseDirs::new() {
-            let home = base_dirs.home_dir().to_string_lossy();
-            return path.replacen("~", &home, 1);
+    let mut record = read_sidecar(&typ_path);
+    if let Some(sent_at) = record.as_ref().and_then(|r| r.sent_at) {
+        let again = Confirm::new(&format!("Already sent on {}. Send again?", sent_at.format("%m/%d/%Y %H:%M")))
+            .with_default(false)
+            .prompt()?;
+        if !again {
+            return Ok(());
         }
     }
-    path.to_string()
-}
 
-// Helper: Open file and reveal in Finder/Explorer
-fn open_and_reveal(path: &Path) {
-    #[cfg(target_os = "macos")]
-    Command::new("open").arg("-R").arg(path).spawn().ok();
+    // Recipient: client email from info.toml, otherwise ask
+    let client_id = invoice_client_id(&typ_path).unwrap_or_default();
+    let client = fs::read_to_string(data_dir.join(&client_id).join("info.toml")).ok()
+        .and_then(|c| toml::from_str::<ClientConfig>(&c).ok());
+    let to = match client.as_ref().and_then(|c| c.email.clone()) {
+        Some(email) => email,
+        None => Text::new("Client Email:").prompt()?,
+    };
+    if to.trim().is_empty() {
+        say!("❌ No recipient. Aborting.");
+        return Ok(());
+    }
 
-    #[cfg(target_os = "windows")]
-    Command::new("explorer").arg(format!("/select,{}", path.to_string_lossy())).spawn().ok();
-    
-    #[cfg(target_os = "linux")]
-    Command::new("xdg-open").arg(path.parent().unwrap()).spawn().ok();
+    let invoice_id = invoice_id_from_path(&typ_path).unwrap_or_default();
+    let client_name = client.as_ref().map(|c| c.name.replace("Attn:", "").trim().to_string()).unwrap_or_default();
+    let total = record.as_ref().map(|r| sender.format_money(r.balance())).unwrap_or_default();
+    let due_date = record.as_ref().and_then(|r| r.due_date).map(|d| sender.format_date(d)).unwrap_or_else(|| "on receipt".to_string());
+    let fill = |template: &str| {
+        template
+            .replace("{invoice_id}", &invoice_id)
+            .replace("{client}", &client_name)
+            .replace("{total}", &total)
+            .replace("{due_date}", &due_date)
+            .replace("{sender}", &sender.name)
+    };
 
-    #[cfg(target_os = "macos")]
-    Command::new("open").arg(path).spawn().ok();
+    let password = match smtp.password.clone().or_else(|| std::env::var("INVOICE_SMTP_PASSWORD").ok()) {
+        Some(p) => p,
+        None => inquire::Password::new("SMTP Password:").without_confirmation().prompt()?,
+    };
 
-    #[cfg(target_os = "windows")]
-    Command::new("explorer").arg(path).spawn().ok();
+    say!("📧 Sending {} to {}...", invoice_id, to.trim());
+    match send_email(smtp, password, to.trim(), &fill(&smtp.subject), &fill(&smtp.body), &pdf_path) {
+        Ok(_) => {
+            say!("✅ Invoice sent.");
+            if let Some(record) = record.as_mut() {
+                record.sent_at = Some(Local::now().naive_local());
+                write_sidecar(&typ_path, record)?;
+            }
+        }
+        Err(e) => say!("❌ Failed to send email: {}", e),
+    }
+    Ok(())
+}
 
-    #[cfg(target_os = "linux")]
-    Command::new("xdg-open").arg(path).spawn().ok();
+fn send_email(smtp: &SmtpConfig, password: String, to: &str, subject: &str, body: &str, pdf_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    use lettre::message::{header::ContentType, Attachment, MultiPart, SinglePart};
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{Message, SmtpTransport, Transport};
+
+    let filename = pdf_path.file_name().unwrap().to_string_lossy().to_string();
+    let attachment = Attachment::new(filename).body(fs::read(pdf_path)?, ContentType::parse("application/pdf")?);
+
+    let email = Message::builder()
+        .from(smtp.from.parse()?)
+        .to(to.parse()?)
+        .subject(subject)
+        .multipart(MultiPart::mixed()
+            .singlepart(SinglePart::plain(body.to_string()))
+            .singlepart(attachment))?;
+
+    // Port 465 uses implicit TLS, anything else STARTTLS
+    let builder = if smtp.port == 465 { SmtpTransport::relay(&smtp.host)? } else { SmtpTransport::starttls_relay(&smtp.host)? };
+    let mailer = builder
+        .port(smtp.port)
+        .credentials(Credentials::new(smtp.username.clone(), password))
+        .build();
+
+    mailer.send(&email)?;
+    Ok(())
 }
 
 // ==========================================
-// 8. Summary Logic
+// 12. Backup, Restore & Archives
 // ==========================================
 
-struct InvoiceInfo {
-    date: NaiveDate,
-    total: f64,
-    is_paid: bool,
-    client: String,
-}
+fn backup_data_root(root: &Path, out: Option<&Path>) {
+    let filename = format!("invoice-maker-backup-{}.zip", Local::now().format("%Y%m%d-%H%M%S"));
+    let archive_path = match out {
+        Some(p) if p.is_dir() => p.join(&filename),
+        Some(p) => p.to_path_buf(),
+        None => PathBuf::from(&filename),
+    };
 
-fn show_summary(root: &Path, year: Option<i32>) {
-    let output_dir = root.join("output");
-    if !output_dir.exists() {
-        println!("❌ No output directory found. No invoices to summarize.");
-        return;
+    say!("📦 Backing up {:?}...", root);
+    match write_backup_zip(root, &archive_path) {
+        Ok(count) => {
+            let size = fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+            say!("✅ Backup created: {:?}", archive_path);
+            say!("   {} file(s), {:.1} KB", count, size as f64 / 1024.0);
+        }
+        Err(e) => {
+            say_err!("❌ Backup failed: {}", e);
+            fs::remove_file(&archive_path).ok();
+        }
     }
+}
 
-    let target_year = year.unwrap_or_else(|| Local::now().year());
-    println!("🔍 Scanning invoices for summary (Year: {})...", target_year);
+// Zip every file under `root` (paths relative to it), skipping the lock file,
+// the trash and the archive itself. Returns the number of files written.
+fn write_backup_zip(root: &Path, archive_path: &Path) -> zip::result::ZipResult<usize> {
+    use std::io::Write;
 
-    // 1. Recursively find all .typ files
-    let mut typ_files = Vec::new();
-    let mut stack = vec![output_dir];
+    let archive_abs = fs::canonicalize(archive_path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")))
+        .map(|dir| dir.join(archive_path.file_name().unwrap_or_default()))
+        .unwrap_or_else(|_| archive_path.to_path_buf());
+
+    let mut zip = zip::ZipWriter::new(fs::File::create(archive_path)?);
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut count = 0;
+    let mut stack = vec![root.to_path_buf()];
     while let Some(dir) = stack.pop() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_dir() {
-                    stack.push(path);
-                } else if path.extension().map_or(false, |e| e == "typ") {
-                    // Exclude VOID invoices from summary
-                    if !path.file_stem().unwrap().to_string_lossy().ends_with("_VOID") {
-                        typ_files.push(path);
-                    }
-                }
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            if relative == Path::new(".lock") || relative.starts_with(".trash") {
+                continue;
+            }
+            if fs::canonicalize(&path).is_ok_and(|p| p == archive_abs) {
+                continue;
+            }
+
+            let name = zip_entry_name(relative);
+            if path.is_dir() {
+                zip.add_directory(format!("{}/", name), options)?;
+                stack.push(path);
+            } else {
+                zip.start_file(name, options)?;
+                zip.write_all(&fs::read(&path)?)?;
+                count += 1;
             }
         }
     }
 
-    if typ_files.is_empty() {
-        println!("No invoices found.");
-        return;
-    }
+    zip.finish()?;
+    Ok(count)
+}
 
-    // 2. Parse date and total amount for each file
-    let mut invoice_infos: Vec<InvoiceInfo> = Vec::new();
-    let date_re = Regex::new(r"HI(\d{8})").unwrap();
+// Zip entries always use '/' separators
+fn zip_entry_name(relative: &Path) -> String {
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
 
-    for path in typ_files {
-        let filename = path.file_name().unwrap().to_string_lossy();
-        
-        if let Some(caps) = date_re.captures(&filename) {
-            let date_str = &caps[1];
-            if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y%m%d") {
-                if let Ok((total, is_paid, client)) = parse_invoice_total(&path) {
-                    invoice_infos.push(InvoiceInfo { date, total, is_paid, client });
-                }
-            }
-        }
-    }
+// --- Yearly archives (output/archive/<year>.zip) ---
 
-    // 3. Group by month and calculate totals
-    // Key: (Year, Month), Value: (Paid, Unpaid)
-    let mut monthly_totals: BTreeMap<(i32, u32), (f64, f64)> = BTreeMap::new();
-    // Key: Client Name, Value: (Paid, Unpaid)
-    let mut client_totals: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+const ARCHIVE_DIR: &str = "archive";
 
-    for info in invoice_infos.iter().filter(|i| i.date.year() == target_year) {
-        // Monthly Aggregation
-        let month_key = (info.date.year(), info.date.month());
-        let entry = monthly_totals.entry(month_key).or_insert((0.0, 0.0));
-        if info.is_paid {
-            entry.0 += info.total;
-        } else {
-            entry.1 += info.total;
+// Move the invoices dated in `year` (whatever the output layout) into output/archive/<year>.zip.
+// The zip also carries their index entries, so summaries read it without unpacking.
+fn archive_year(root: &Path, year: i32) {
+    let output_dir = root.join("output");
+    let archive_dir = output_dir.join(ARCHIVE_DIR);
+    let archive_path = archive_dir.join(format!("{}.zip", year));
+    // Invoices of a year archived before are added to its zip
+    let mut archived = InvoiceIndex::default();
+    if archive_path.exists() {
+        match read_archive_index(&archive_path) {
+            Some(index) => archived = index,
+            None => {
+                say_err!("❌ {:?} is unreadable; move it aside to archive {} again.", archive_path, year);
+                return;
+            }
         }
+    }
 
-        // Client Aggregation
-        let client_entry = client_totals.entry(info.client.clone()).or_insert((0.0, 0.0));
-        if info.is_paid {
-            client_entry.0 += info.total;
-        } else {
-            client_entry.1 += info.total;
+    // Files of each invoice, its .typ first
+    let mut index = InvoiceIndex::default();
+    let mut invoices: Vec<(String, Vec<PathBuf>)> = Vec::new();
+    for typ_path in collect_files(&output_dir, "typ") {
+        let Some(entry) = index_entry(&typ_path) else { continue };
+        if entry.info.date.year() != year {
+            continue;
         }
+        let key = index_key(&output_dir, &typ_path);
+        let files = [Some(typ_path.clone()), Some(typ_path.with_extension("pdf")), sidecar_path(&typ_path), payment_qr_path(&typ_path)]
+            .into_iter()
+            .flatten()
+            .filter(|p| p.exists())
+            .collect();
+        invoices.push((key.clone(), files));
+        index.entries.insert(key, entry);
+    }
+    let files: Vec<PathBuf> = invoices.iter().flat_map(|(_, files)| files.clone()).collect();
+    if index.entries.is_empty() {
+        say!("❌ No invoices dated {} found.", year);
+        return;
     }
 
-    // 4. Create table using comfy-table (Monthly)
-    let mut table = Table::new();
-    table.set_header(vec![
-        Cell::new("Month"),
-        Cell::new("Paid"),
-        Cell::new("Unpaid"),
-        Cell::new("Total"),
-    ]);
-
-    let mut total_paid = 0.0;
-    let mut total_unpaid = 0.0;
-
-    for ((year, month), (paid, unpaid)) in monthly_totals.iter().rev() {
-        let month_str = NaiveDate::from_ymd_opt(*year, *month, 1).unwrap().format("%B %Y").to_string();
-        let total = paid + unpaid;
+    let target = if archived.entries.is_empty() { "into" } else { "into the existing" };
+    let preview = vec![format!("Move {} invoice(s) ({} files) {} {}", index.entries.len(), files.len(), target, index_key(&output_dir, &archive_path))];
+    if !confirm_destructive(root, &preview, &[]) {
+        return;
+    }
 
-        let unpaid_cell = if *unpaid > 0.0 {
-            // Cell::new(format!("${:.2}", unpaid)).fg(Color::Rgb { r: 185, g: 28, b: 28 })
-            Cell::new(format!("${:.2}", unpaid)).fg(Color::Red)
-        } else {
-            Cell::new(format!("${:.2}", unpaid))
-        };
+    if let Err(e) = fs::create_dir_all(&archive_dir) {
+        say_err!("❌ Failed to create {:?}: {}", archive_dir, e);
+        return;
+    }
+    archived.entries.extend(index.entries.clone());
+    if let Err(e) = write_archive_zip(&output_dir, &archive_path, &files, &archived) {
+        say_err!("❌ Archive failed: {}", e);
+        return;
+    }
 
-        let paid_cell = if *paid > 0.0 {
-            // Cell::new(format!("${:.2}", paid)).fg(Color::Rgb { r: 4, g: 120, b: 87 })
-            Cell::new(format!("${:.2}", paid)).fg(Color::Green)
-        } else {
-            Cell::new(format!("${:.2}", paid))
-        };
+    // Only now that the zip is complete are the loose files removed, then folders left empty.
+    // An invoice whose .typ can't be removed stays live and whole: its other files are kept.
+    let mut kept = Vec::new();
+    let mut failed = 0;
+    for (key, files) in &invoices {
+        for (i, file) in files.iter().enumerate() {
+            if let Err(e) = fs::remove_file(file) {
+                say_err!("⚠️  Could not remove {:?}: {}", file, e);
+                failed += 1;
+                if i == 0 {
+                    kept.push(key.clone());
+                    break;
+                }
+                continue;
+            }
+            for dir in file.ancestors().skip(1).take_while(|d| *d != output_dir) {
+                if fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
+        }
+    }
 
-        table.add_row(vec![
-            Cell::new(month_str),
-            paid_cell,
-            unpaid_cell,
-            Cell::new(format!("${:.2}", total)),
-        ]);
-        total_paid += paid;
-        total_unpaid += unpaid;
+    // The archive must not count invoices that stayed live too
+    if !kept.is_empty() {
+        archived.entries.retain(|key, _| !kept.contains(key));
+        index.entries.retain(|key, _| !kept.contains(key));
+        if let Err(e) = write_archive_zip(&output_dir, &archive_path, &[], &archived) {
+            say_err!("❌ Failed to update the archive index: {}", e);
+            say_err!("   `summary {} --archived` will count these invoice(s) twice: {}", year, kept.join(", "));
+        }
     }
 
-    let total_unpaid_cell = Cell::new(format!("${:.2}", total_unpaid)).add_attribute(Attribute::Bold);
-    let total_unpaid_cell = if total_unpaid > 0.0 {
-        // total_unpaid_cell.fg(Color::Rgb { r: 185, g: 28, b: 28 })
-        total_unpaid_cell.fg(Color::Red)
-    } else {
-        total_unpaid_cell
-    };
+    let mut current = load_index(&output_dir);
+    current.entries.retain(|key, _| !index.entries.contains_key(key));
+    save_index(&output_dir, &current);
 
-    let total_paid_cell = Cell::new(format!("${:.2}", total_paid)).add_attribute(Attribute::Bold);
-    let total_paid_cell = if total_paid > 0.0 {
-        // total_paid_cell.fg(Color::Rgb { r: 4, g: 120, b: 87 })
-        total_paid_cell.fg(Color::Green)
-    } else {
-        total_paid_cell
-    };
+    let size = fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+    say!("✅ Archived {} invoice(s) to {:?} ({:.1} KB)", index.entries.len(), archive_path, size as f64 / 1024.0);
+    if failed > 0 {
+        say!("⚠️  {} file(s) could not be removed; {} invoice(s) stay in output/ and were left out of the archive index.", failed, kept.len());
+    }
+    say!("   Use `summary {} --archived` to include them in summaries.", year);
+}
 
-    table.add_row(vec![
-        Cell::new(format!("Total ({})", target_year)).add_attribute(Attribute::Bold),
-        total_paid_cell,
-        total_unpaid_cell,
-        Cell::new(format!("${:.2}", total_paid + total_unpaid)).add_attribute(Attribute::Bold),
-    ]);
+// Write `files` and `index` to the zip at `archive_path`, keeping the other entries of an
+// existing zip. Built in a temp file first, so a failure leaves the old zip untouched.
+fn write_archive_zip(output_dir: &Path, archive_path: &Path, files: &[PathBuf], index: &InvoiceIndex) -> zip::result::ZipResult<()> {
+    use std::io::Write;
+
+    let file_name = archive_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = archive_path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let result = (|| {
+        let names: Vec<String> = files.iter().map(|p| zip_entry_name(p.strip_prefix(output_dir).unwrap_or(p))).collect();
+        let mut zip = zip::ZipWriter::new(fs::File::create(&tmp)?);
+        if archive_path.exists() {
+            let mut existing = zip::ZipArchive::new(fs::File::open(archive_path)?)?;
+            for i in 0..existing.len() {
+                let entry = existing.by_index_raw(i)?;
+                if entry.name() != INDEX_FILE && !names.iter().any(|n| n == entry.name()) {
+                    zip.raw_copy_file(entry)?;
+                }
+            }
+        }
 
-    println!("\n--- Monthly Invoice Summary ({}) ---", target_year);
-    println!("{table}");
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (path, name) in files.iter().zip(&names) {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(&fs::read(path)?)?;
+        }
+        zip.start_file(INDEX_FILE, options)?;
+        zip.write_all(serde_json::to_string(index).unwrap_or_default().as_bytes())?;
+        zip.finish()?;
+        fs::rename(&tmp, archive_path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        fs::remove_file(&tmp).ok();
+    }
+    result
+}
 
-    // 5. Client Summary Table
-    let mut client_table = Table::new();
-    client_table.set_header(vec![
-        Cell::new("Client"),
-        Cell::new("Paid"),
-        Cell::new("Unpaid"),
-        Cell::new("Total"),
-    ]);
+// The index.json inside an archive zip
+fn read_archive_index(path: &Path) -> Option<InvoiceIndex> {
+    use std::io::Read;
 
-    // Sort clients by total amount descending
-    let mut client_vec: Vec<_> = client_totals.into_iter().collect();
-    client_vec.sort_by(|a, b| (b.1.0 + b.1.1).partial_cmp(&(a.1.0 + a.1.1)).unwrap());
+    let mut archive = zip::ZipArchive::new(fs::File::open(path).ok()?).ok()?;
+    let mut content = String::new();
+    archive.by_name(INDEX_FILE).ok()?.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
 
-    for (client, (paid, unpaid)) in client_vec {
-        let total = paid + unpaid;
+// Index entries of every archived year, read from the index.json inside each zip
+fn archived_invoices(output_dir: &Path) -> Vec<IndexEntry> {
+    let mut zips: Vec<PathBuf> = fs::read_dir(output_dir.join(ARCHIVE_DIR))
+        .map(|entries| entries.flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "zip"))
+            .collect())
+        .unwrap_or_default();
+    zips.sort();
+
+    let mut entries = Vec::new();
+    for path in zips {
+        match read_archive_index(&path) {
+            Some(index) => entries.extend(index.entries.into_values()),
+            None => say!("⚠️  Skipping unreadable archive {:?}", path),
+        }
+    }
+    entries
+}
 
-        let unpaid_cell = if unpaid > 0.0 {
-            // Cell::new(format!("${:.2}", unpaid)).fg(Color::Rgb { r: 185, g: 28, b: 28 })
-            Cell::new(format!("${:.2}", unpaid)).fg(Color::Red)
-        } else {
-            Cell::new(format!("${:.2}", unpaid))
-        };
+fn restore_backup(root: &Path, file: &Path, force: bool) {
+    let reader = match fs::File::open(file) {
+        Ok(f) => f,
+        Err(e) => { say_err!("❌ Failed to open {:?}: {}", file, e); return; }
+    };
+    let mut archive = match zip::ZipArchive::new(reader) {
+        Ok(a) => a,
+        Err(e) => { say_err!("❌ Failed to open zip: {}", e); return; }
+    };
 
-        let paid_cell = if paid > 0.0 {
-            // Cell::new(format!("${:.2}", paid)).fg(Color::Rgb { r: 4, g: 120, b: 87 })
-            Cell::new(format!("${:.2}", paid)).fg(Color::Green)
-        } else {
-            Cell::new(format!("${:.2}", paid))
+    // Validate before touching anything: entries must stay inside the root
+    // and the archive must look like a data root
+    let mut targets: Vec<(usize, PathBuf)> = Vec::new();
+    let mut has_clients = false;
+    for i in 0..archive.len() {
+        let Ok(entry) = archive.by_index(i) else { continue };
+        let Some(relative) = entry.enclosed_name() else {
+            say_err!("❌ Unsafe path in archive: {}", entry.name());
+            return;
         };
+        has_clients |= relative.starts_with("data/clients");
+        if !entry.is_dir() {
+            targets.push((i, relative));
+        }
+    }
 
-        client_table.add_row(vec![
-            Cell::new(client),
-            paid_cell,
-            unpaid_cell,
-            Cell::new(format!("${:.2}", total)),
-        ]);
+    if !has_clients {
+        say_err!("❌ {:?} doesn't look like an invoice-maker backup (no data/clients folder).", file);
+        return;
     }
 
-    println!("\n--- Client Summary ({}) ---", target_year);
-    println!("{client_table}");
-}
+    let existing: Vec<&PathBuf> = targets.iter().map(|(_, rel)| rel).filter(|rel| root.join(rel).exists()).collect();
+    if !existing.is_empty() && !force {
+        say!("❌ {} file(s) already exist in {:?}, e.g.:", existing.len(), root);
+        for rel in existing.iter().take(5) {
+            say!("   - {}", rel.to_string_lossy());
+        }
+        say!("   Re-run with --force to overwrite them.");
+        return;
+    }
 
+    say!("📦 Restoring {} file(s) into {:?}...", targets.len(), root);
+    for (i, relative) in &targets {
+        let target = root.join(relative);
+        let result = archive.by_index(*i).map_err(std::io::Error::other).and_then(|mut entry| {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = fs::File::create(&target)?;
+            std::io::copy(&mut entry, &mut out).map(|_| ())
+        });
+        if let Err(e) = result {
+            say_err!("❌ Failed to restore {}: {}", relative.to_string_lossy(), e);
+            return;
+        }
+    }
 
-fn parse_invoice_total(path: &Path) -> Result<(f64, bool, String), std::io::Error> {
-    let content = fs::read_to_string(path)?;
+    say!("✅ Restore complete.");
+}
 
-    // Use global search for amount and tax_rate, which is more robust
-    let amount_re = Regex::new(r#"amount:\s*([\d\.]+)"#).unwrap();
-    let tax_re = Regex::new(r"tax_rate:\s*([\d\.]+)").unwrap();
-    let paid_re = Regex::new(r"is_paid:\s*(true|false)").unwrap();
-    let client_re = Regex::new(r#"client:\s*\(\s*name:\s*"([^"]+)""#).unwrap();
+// ==========================================
+// 13. Client Statements
+// ==========================================
 
-    let mut subtotal = 0.0;
+// All invoices of one client (VOID and estimates excluded) rendered with
+// statement.tera into statements/<client_id>_<date>.pdf
+fn create_statement(root: &Path, data_dir: &Path, sender: &SenderConfig) -> anyhow::Result<()> {
+    let Some(client_id) = select_client(data_dir, "Select Client for Statement:") else { return Ok(()) };
+    let client = load_client_config(data_dir, &client_id)?;
 
-    // Sum all amounts found in the file
-    for cap in amount_re.captures_iter(&content) {
-        if let Ok(amount) = cap[1].parse::<f64>() {
-            subtotal += amount;
-        }
+    let mut infos: Vec<InvoiceInfo> = client_invoices(&root.join("output"), &client_id)
+        .into_iter()
+        .filter(|p| !invoice_status(p).is_void() && !is_estimate(p))
+        .filter_map(|p| invoice_info(&p))
+        .collect();
+    if infos.is_empty() {
+        say!("❌ No invoices found for {}.", client_id);
+        return Ok(());
     }
-    
-    // Get tax_rate
-    let tax_rate = if let Some(tax_cap) = tax_re.captures(&content) {
-        tax_cap[1].parse::<f64>().unwrap_or(0.0)
-    } else {
-        0.0
-    };
+    infos.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+    let (mut total_invoiced, mut total_paid) = (0.0, 0.0);
+    let lines: Vec<StatementLine> = infos.iter()
+        .map(|info| {
+            let paid = if info.status.is_paid() { info.total } else { info.amount_paid };
+            total_invoiced += info.total;
+            total_paid += paid;
+            let status = if !info.status.is_paid() && info.amount_paid > 0.0 { "PARTIAL" } else { info.status.label() };
+            StatementLine {
+                date: sender.format_date(info.date),
+                id: info.id.clone(),
+                status: status.to_string(),
+                total: sender.format_money(info.total),
+                paid: sender.format_money(paid),
+                balance: sender.format_money(info.total - paid),
+            }
+        })
+        .collect();
+    let outstanding = total_invoiced - total_paid;
 
-    // Get is_paid status
-    let is_paid = if let Some(paid_cap) = paid_re.captures(&content) {
-        &paid_cap[1] == "true"
-    } else {
-        false
+    let today = Local::now().date_naive();
+    let context_data = StatementContext {
+        date: sender.format_date(today),
+        sender: sender.clone(),
+        client: client.clone(),
+        lines,
+        total_invoiced: sender.format_money(total_invoiced),
+        total_paid: sender.format_money(total_paid),
+        total_outstanding: sender.format_money(outstanding),
+        paper: sender.paper().unwrap_or("us-letter").to_string(),
+        accent_color: sender.accent_color.clone(),
+        doc_title: format!("Statement - {}", client.name),
     };
 
-    // Get client name
-    let client_name = if let Some(client_cap) = client_re.captures(&content) {
-        client_cap[1].replace("Attn:", "").trim().to_string()
-    } else {
-        "Unknown Client".to_string()
-    };
+    let template_dir = init_templates(root)?;
+    let tera = load_templates(&template_dir, sender)
+        .map_err(|e| anyhow::anyhow!("Template Error: {}", e))?;
+    let rendered = tera.render(STATEMENT_TEMPLATE_NAME, &Context::from_serialize(&context_data)?)
+        .map_err(|e| anyhow::anyhow!("Template Error: {:?}", e))?;
+
+    let statement_dir = root.join("statements");
+    fs::create_dir_all(&statement_dir).with_context(|| format!("Failed to create {:?}", statement_dir))?;
+    let stem = format!("{}_{}", client_id, today.format("%Y%m%d"));
+    let typ_path = statement_dir.join(format!("{}.typ", stem));
+    let pdf_path = statement_dir.join(format!("{}.pdf", stem));
+    fs::write(&typ_path, rendered).with_context(|| format!("Failed to write {:?}", typ_path))?;
+
+    say!("📄 {} invoice(s), outstanding: {}", infos.len(), sender.format_money(outstanding));
+    if !typst_installed() {
+        say!("⚠️  'typst' is not installed, statements need it (brew install typst). Source saved to: {:?}", typ_path);
+        return Ok(());
+    }
 
-    Ok((subtotal * (1.0 + tax_rate), is_paid, client_name))
+    say!("\n🔨 Compiling PDF...");
+    match compile_typst(root, &typ_path, &pdf_path) {
+        Ok(()) => {
+            say!("✅ Statement Generated: {:?}", pdf_path);
+            open_and_reveal(&pdf_path);
+        }
+        Err(e) => print_typst_error(&e),
+    }
+    Ok(())
 }
 
 // ==========================================
@@ -1275,9 +5916,9 @@ struct GitHubAsset {
     browser_download_url: String,
 }
 
-fn check_and_update() {
-    println!("🔍 Checking for updates...");
-    println!("   Current version: v{}", CURRENT_VERSION);
+fn check_and_update() -> anyhow::Result<()> {
+    say!("🔍 Checking for updates...");
+    say!("   Current version: v{}", CURRENT_VERSION);
 
     // Fetch latest release from GitHub API
     let api_url = format!("https://api.github.com/repos/{}/releases/latest", GITHUB_REPO);
@@ -1285,55 +5926,55 @@ fn check_and_update() {
     let client = reqwest::blocking::Client::builder()
         .user_agent("invoice-maker-updater")
         .build()
-        .expect("Failed to create HTTP client");
+        .context("Failed to create HTTP client")?;
 
     let response = match client.get(&api_url).send() {
         Ok(resp) => resp,
         Err(e) => {
-            eprintln!("❌ Failed to check for updates: {}", e);
-            return;
+            say_err!("❌ Failed to check for updates: {}", e);
+            return Ok(());
         }
     };
 
     if !response.status().is_success() {
-        eprintln!("❌ Failed to fetch release info: HTTP {}", response.status());
-        return;
+        say_err!("❌ Failed to fetch release info: HTTP {}", response.status());
+        return Ok(());
     }
 
     let release: GitHubRelease = match response.json() {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("❌ Failed to parse release info: {}", e);
-            return;
+            say_err!("❌ Failed to parse release info: {}", e);
+            return Ok(());
         }
     };
 
     // Parse versions for comparison
     let latest_version = release.tag_name.trim_start_matches('v');
-    println!("   Latest version:  v{}", latest_version);
+    say!("   Latest version:  v{}", latest_version);
 
     let current = match semver::Version::parse(CURRENT_VERSION) {
         Ok(v) => v,
         Err(_) => {
-            eprintln!("❌ Failed to parse current version");
-            return;
+            say_err!("❌ Failed to parse current version");
+            return Ok(());
         }
     };
 
     let latest = match semver::Version::parse(latest_version) {
         Ok(v) => v,
         Err(_) => {
-            eprintln!("❌ Failed to parse latest version");
-            return;
+            say_err!("❌ Failed to parse latest version");
+            return Ok(());
         }
     };
 
     if current >= latest {
-        println!("✅ You're already on the latest version!");
-        return;
+        say!("✅ You're already on the latest version!");
+        return Ok(());
     }
 
-    println!("\n🆕 New version available: v{} -> v{}", CURRENT_VERSION, latest_version);
+    say!("\n🆕 New version available: v{} -> v{}", CURRENT_VERSION, latest_version);
 
     // Find the macOS zip asset
     let zip_asset = release.assets.iter().find(|a| {
@@ -1344,12 +5985,12 @@ fn check_and_update() {
     let asset = match zip_asset {
         Some(a) => a,
         None => {
-            eprintln!("❌ No compatible release asset found");
-            println!("   Available assets:");
+            say_err!("❌ No compatible release asset found");
+            say!("   Available assets:");
             for a in &release.assets {
-                println!("   - {}", a.name);
+                say!("   - {}", a.name);
             }
-            return;
+            return Ok(());
         }
     };
 
@@ -1360,52 +6001,51 @@ fn check_and_update() {
         .unwrap_or(false);
 
     if !confirm {
-        println!("⏸️  Update cancelled.");
-        return;
+        say!("⏸️  Update cancelled.");
+        return Ok(());
     }
 
     // Ask for install path
     let install_path = Text::new("Install path:")
         .with_default(DEFAULT_INSTALL_PATH)
-        .prompt()
-        .unwrap();
+        .prompt()?;
 
     let install_path = expand_home_dir(&install_path);
     let install_path = PathBuf::from(&install_path);
 
     // Download the zip file
-    println!("📥 Downloading {}...", asset.name);
+    say!("📥 Downloading {}...", asset.name);
     
     let zip_response = match client.get(&asset.browser_download_url).send() {
         Ok(resp) => resp,
         Err(e) => {
-            eprintln!("❌ Failed to download: {}", e);
-            return;
+            say_err!("❌ Failed to download: {}", e);
+            return Ok(());
         }
     };
 
     if !zip_response.status().is_success() {
-        eprintln!("❌ Download failed: HTTP {}", zip_response.status());
-        return;
+        say_err!("❌ Download failed: HTTP {}", zip_response.status());
+        return Ok(());
     }
 
     let zip_bytes = match zip_response.bytes() {
         Ok(b) => b,
         Err(e) => {
-            eprintln!("❌ Failed to read download: {}", e);
-            return;
+            say_err!("❌ Failed to read download: {}", e);
+            return Ok(());
         }
     };
 
     // Extract the binary from zip
-    println!("📦 Extracting...");
+    say!("📦 Extracting...");
     
     let reader = std::io::Cursor::new(zip_bytes);
     let mut archive = match zip::ZipArchive::new(reader) {
         Ok(a) => a,
         Err(e) => {
-            eprintln!("❌ Failed to open zip: {}", e);
-            return;
+            say_err!("❌ Failed to open zip: {}", e);
+            return Ok(());
         }
     };
 
@@ -1419,42 +6059,41 @@ fn check_and_update() {
         };
         
         let name = file.name().to_lowercase();
-        if name.contains("invoice-maker") || name == "im" {
-            if !name.ends_with('/') && !name.contains('.') || name.ends_with("invoice-maker") {
-                let mut data = Vec::new();
-                std::io::Read::read_to_end(&mut file, &mut data).ok();
-                binary_data = Some(data);
-                println!("   Found binary: {}", file.name());
-                break;
-            }
+        if (name.contains("invoice-maker") || name == "im")
+            && (!name.ends_with('/') && !name.contains('.') || name.ends_with("invoice-maker"))
+        {
+            let mut data = Vec::new();
+            std::io::Read::read_to_end(&mut file, &mut data).ok();
+            binary_data = Some(data);
+            say!("   Found binary: {}", file.name());
+            break;
         }
     }
 
     let binary_data = match binary_data {
         Some(d) => d,
         None => {
-            eprintln!("❌ Could not find binary in zip archive");
-            println!("   Archive contents:");
+            say_err!("❌ Could not find binary in zip archive");
+            say!("   Archive contents:");
             for i in 0..archive.len() {
                 if let Ok(file) = archive.by_index(i) {
-                    println!("   - {}", file.name());
+                    say!("   - {}", file.name());
                 }
             }
-            return;
+            return Ok(());
         }
     };
 
     // Write the new binary
-    println!("📝 Installing to {}...", install_path.display());
+    say!("📝 Installing to {}...", install_path.display());
 
     // Create parent directory if needed
-    if let Some(parent) = install_path.parent() {
-        if !parent.exists() {
-            if let Err(e) = fs::create_dir_all(parent) {
-                eprintln!("❌ Failed to create directory: {}", e);
-                return;
-            }
-        }
+    if let Some(parent) = install_path.parent()
+        && !parent.exists()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        say_err!("❌ Failed to create directory: {}", e);
+        return Ok(());
     }
 
     // Write binary (may need sudo for /usr/local/bin)
@@ -1462,13 +6101,13 @@ fn check_and_update() {
         Ok(_) => {}
         Err(e) => {
             if e.kind() == std::io::ErrorKind::PermissionDenied {
-                println!("⚠️  Permission denied. Trying with sudo...");
+                say!("⚠️  Permission denied. Trying with sudo...");
                 
                 // Write to temp file first
                 let temp_path = std::env::temp_dir().join("im_update_temp");
                 if let Err(e) = fs::write(&temp_path, &binary_data) {
-                    eprintln!("❌ Failed to write temp file: {}", e);
-                    return;
+                    say_err!("❌ Failed to write temp file: {}", e);
+                    return Ok(());
                 }
                 
                 // Use sudo to move the file
@@ -1484,13 +6123,13 @@ fn check_and_update() {
                             .status();
                     }
                     _ => {
-                        eprintln!("❌ Failed to install with sudo");
-                        return;
+                        say_err!("❌ Failed to install with sudo");
+                        return Ok(());
                     }
                 }
             } else {
-                eprintln!("❌ Failed to write binary: {}", e);
-                return;
+                say_err!("❌ Failed to write binary: {}", e);
+                return Ok(());
             }
         }
     }
@@ -1506,6 +6145,191 @@ fn check_and_update() {
         }
     }
 
-    println!("✅ Successfully updated to v{}!", latest_version);
-    println!("   Installed at: {}", install_path.display());
-}
\ No newline at end of file
+    say!("✅ Successfully updated to v{}!", latest_version);
+    say!("   Installed at: {}", install_path.display());
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The tail of a printed .typ: template defaults, then the injected call
+    const TYP: &str = r#"#let invoice(..args) = {}
+#set page(paper: "us-letter")
+#invoice(
+  invoice_id: "INV20261017-01",
+  client: (
+    name: "Acme (West) \"Co\"",
+    address: [1 Main St \ Suite 2 \ Springfield, IL 62701 \ USA],
+  ),
+  is_paid: false,
+  is_void: false
+)
+"#;
+
+    fn record(id: &str, client_id: &str) -> InvoiceRecord {
+        let json = format!(
+            r#"{{"id": "{}", "date": "2026-10-17", "client_id": "{}", "client_name": "Acme",
+            "project_id": "main-st", "items": [], "subtotal": 100.0, "tax_rate": 0.0, "tax_amount": 0.0,
+            "tax_status": "Exempt", "total": 100.0, "is_paid": false, "is_void": false}}"#,
+            id, client_id
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    // Fresh directory under the system temp dir, removed by the caller
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("invoice-maker-test-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_item_arg_qty_rate_and_amount_forms() {
+        let item = parse_item_arg("Design:2:50").unwrap();
+        assert_eq!((item.description.as_str(), item.quantity, item.rate, item.amount), ("Design", 2.0, 50.0, 100.0));
+
+        let item = parse_item_arg("Work:$1,250").unwrap();
+        assert_eq!((item.description.as_str(), item.quantity, item.amount), ("Work", 1.0, 1250.0));
+    }
+
+    #[test]
+    fn parse_item_arg_keeps_colons_in_the_description() {
+        let item = parse_item_arg("Phase 1: design:3:10").unwrap();
+        assert_eq!((item.description.as_str(), item.quantity, item.rate), ("Phase 1: design", 3.0, 10.0));
+        // A middle part that is not a number belongs to the description
+        let item = parse_item_arg("a:b:10").unwrap();
+        assert_eq!((item.description.as_str(), item.quantity, item.rate), ("a:b", 1.0, 10.0));
+    }
+
+    #[test]
+    fn parse_item_arg_rejects_bad_items() {
+        assert!(parse_item_arg("Work").is_err());
+        assert!(parse_item_arg(":10").is_err());
+        assert!(parse_item_arg("Work:-5").is_err());
+        assert!(parse_item_arg("Work:2:abc").is_err());
+    }
+
+    #[test]
+    fn parse_amount_strips_symbols_and_separators() {
+        assert_eq!(parse_amount("$1,250.00").unwrap(), 1250.0);
+        assert_eq!(parse_amount(" 42 ").unwrap(), 42.0);
+        assert!(parse_amount("12abc").is_err());
+    }
+
+    #[test]
+    fn parse_tax_arg_accepts_percentages_in_range() {
+        assert_eq!(parse_tax_arg("8.25%").unwrap(), 8.25);
+        assert_eq!(parse_tax_arg("0").unwrap(), 0.0);
+        assert!(parse_tax_arg("100.5").is_err());
+        assert!(parse_tax_arg("-1").is_err());
+        assert!(parse_tax_arg("abc").is_err());
+    }
+
+    #[test]
+    fn bump_invoice_id_increments_the_sequence() {
+        assert_eq!(bump_invoice_id("HI20251214-01"), "HI20251214-02");
+        assert_eq!(bump_invoice_id("HI20251214-09"), "HI20251214-10");
+        assert_eq!(bump_invoice_id("HI20251214"), "HI20251214-02");
+    }
+
+    #[test]
+    fn aging_bucket_boundaries() {
+        assert_eq!(aging_bucket(-5), 0);
+        assert_eq!(aging_bucket(0), 0);
+        assert_eq!(aging_bucket(1), 1);
+        assert_eq!(aging_bucket(30), 1);
+        assert_eq!(aging_bucket(31), 2);
+        assert_eq!(aging_bucket(90), 3);
+        assert_eq!(aging_bucket(91), 4);
+    }
+
+    #[test]
+    fn filename_regex_captures_the_id_of_custom_patterns() {
+        let re = filename_regex("{client}_{id}");
+        assert_eq!(&re.captures("acme_INV20261017-01").unwrap()[1], "INV20261017-01");
+        let re = filename_regex("{date} {id}");
+        assert_eq!(&re.captures("2026-10-17_INV20261017-03").unwrap()[1], "INV20261017-03");
+        assert!(filename_regex(DEFAULT_FILENAME_PATTERN).captures("notes").is_none());
+    }
+
+    #[test]
+    fn invoice_id_survives_every_status_suffix() {
+        for status in [InvoiceStatus::Unpaid, InvoiceStatus::Paid, InvoiceStatus::Void] {
+            let path = PathBuf::from(format!("output/2026/acme/INV20261017-01_main-st{}.typ", status.suffix()));
+            assert_eq!(invoice_id_from_path(&path).as_deref(), Some("INV20261017-01"));
+            assert_eq!(invoice_status(&path), status);
+        }
+        assert_eq!(invoice_id_from_path(Path::new("output/readme.typ")), None);
+    }
+
+    #[test]
+    fn status_fields_round_trip() {
+        assert!(has_status_fields(TYP));
+        for (is_paid, is_void) in [(true, false), (false, true), (true, true), (false, false)] {
+            let updated = set_status_fields(TYP, is_paid, is_void);
+            assert!(updated.contains(&format!("is_paid: {}", is_paid)));
+            assert!(updated.contains(&format!("is_void: {}", is_void)));
+            assert_eq!(set_status_fields(&updated, false, false), TYP);
+        }
+        // Only the injected call counts, not the template's defaults
+        let template_only = "#let invoice(is_paid: false, is_void: false) = {}\n";
+        assert!(!has_status_fields(template_only));
+    }
+
+    #[test]
+    fn typ_block_skips_parentheses_in_strings_and_markup() {
+        let client = typ_block(TYP, "client").unwrap();
+        assert!(client.contains(r#"name: "Acme (West) \"Co\"""#));
+        assert!(client.trim_end().ends_with("62701 \\ USA],"));
+        assert_eq!(typ_block(TYP, "project"), None);
+    }
+
+    #[test]
+    fn parse_typ_address_splits_lines() {
+        let address = parse_typ_address(r"1 Main St \ Suite 2 \ Springfield, IL 62701 \ USA");
+        assert_eq!(address.street, "1 Main St");
+        assert_eq!(address.street2.as_deref(), Some("Suite 2"));
+        assert_eq!((address.city.as_str(), address.state.as_str(), address.zip.as_str()), ("Springfield", "IL", "62701"));
+        assert_eq!(address.country.as_deref(), Some("USA"));
+
+        let address = parse_typ_address(r"5 Elm \#3 \ Portland, OR 97201");
+        assert_eq!(address.street, "5 Elm #3");
+        assert_eq!((address.street2, address.country), (None, None));
+        assert_eq!(address.city, "Portland");
+    }
+
+    #[test]
+    fn layout_dir_fills_placeholders_inside_output() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        let root = Path::new("/data");
+        assert_eq!(layout_dir(root, DEFAULT_OUTPUT_LAYOUT, date, "acme"), root.join("output/2026/acme"));
+        assert_eq!(layout_dir(root, "{client}/{year}-{month}", date, "acme"), root.join("output/acme/2026-10"));
+        // No way out of output/
+        assert_eq!(layout_dir(root, "../{year}/./{client}/", date, "acme"), root.join("output/2026/acme"));
+    }
+
+    #[test]
+    fn client_lookup_reads_the_sidecar_under_a_custom_layout() {
+        let root = temp_root("layout");
+        let date = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+        // With {year}/{month} the folder is "10", not the client
+        let dir = layout_dir(&root, "{year}/{month}", date, "acme");
+        fs::create_dir_all(&dir).unwrap();
+        let typ_path = dir.join("INV20261017-01_main-st_PAID.typ");
+        fs::write(&typ_path, TYP).unwrap();
+        write_sidecar(&typ_path, &record("INV20261017-01", "acme")).unwrap();
+
+        assert!(dir.join("INV20261017-01.json").exists());
+        assert_eq!(invoice_client_id(&typ_path).as_deref(), Some("acme"));
+        assert_eq!(client_invoices(&root.join("output"), "acme"), vec![typ_path.clone()]);
+
+        // Legacy invoices without a sidecar fall back to the folder name
+        let legacy = dir.join("INV20261017-02_main-st.typ");
+        fs::write(&legacy, TYP).unwrap();
+        assert_eq!(invoice_client_id(&legacy).as_deref(), Some("10"));
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,11 +1,26 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Address {
     pub street: String,
+    // Suite, unit or building name, printed below the street
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street2: Option<String>,
     pub city: String,
     pub state: String,
     pub zip: String,
+    // Printed as its own line when set; None for domestic addresses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+}
+
+// ZIP lookup only knows US codes; no country counts as US
+pub fn is_us_country(country: Option<&str>) -> bool {
+    match country.map(|c| c.trim().to_uppercase()) {
+        None => true,
+        Some(c) => c.is_empty() || ["US", "USA", "UNITED STATES", "UNITED STATES OF AMERICA"].contains(&c.as_str()),
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -13,16 +28,83 @@ pub struct Project {
     pub id: String,
     pub name: Option<String>,
     pub address: Address,
+    // Default tax rate in percent (e.g. 8.875) for this job site
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
 }
 
+// Version of the on-disk TOML formats (settings.toml, sender.toml, info.toml).
+// Files without `schema_version` are version 0; `migrate` upgrades them, loading never does.
+pub const SCHEMA_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ClientConfig {
+    #[serde(default)]
+    pub schema_version: u32,
     pub name: String,         // 公司名 或 人名
     pub attn: Option<String>, // 新增：联系人
     pub email: Option<String>,
     pub billing_address: Option<Address>,
     #[serde(default)] 
     pub projects: Vec<Project>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recurring: Vec<RecurringInvoice>,
+    // Tax rate in percent for projects without their own tax_rate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_tax_rate: Option<f64>,
+    // e.g. resellers: new invoices get no tax and the "Exempt" status
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tax_exempt: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Cadence {
+    #[default]
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Cadence {
+    pub fn months(&self) -> i32 {
+        match self {
+            Cadence::Monthly => 1,
+            Cadence::Quarterly => 3,
+            Cadence::Yearly => 12,
+        }
+    }
+}
+
+// Invoice billed on a fixed schedule, defined under `[[recurring]]` in info.toml
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecurringInvoice {
+    pub id: String,
+    pub project_id: String,
+    pub items: Vec<InvoiceItem>,
+    // Tax rate in percent; None or 0 means exempt
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+    #[serde(default)]
+    pub cadence: Cadence,
+    #[serde(default)]
+    pub terms_days: Option<u32>,
+    #[serde(default)]
+    pub last_generated: Option<NaiveDate>,
+}
+
+impl RecurringInvoice {
+    // Due when never generated, or when a full cadence has passed since the last run (by month)
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        use chrono::Datelike;
+        match self.last_generated {
+            None => true,
+            Some(last) => {
+                let elapsed = (today.year() - last.year()) * 12 + today.month() as i32 - last.month() as i32;
+                elapsed >= self.cadence.months()
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -31,17 +113,472 @@ pub struct InvoiceItem {
     pub quantity: f64,
     pub rate: f64,
     pub amount: f64, 
+    // Whether tax applies to this line (e.g. materials yes, labor no)
+    #[serde(default = "default_taxable")]
+    pub taxable: bool,
+    // Unit of the quantity, e.g. "hours", "each" or "sq ft"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+fn default_taxable() -> bool {
+    true
+}
+
+impl InvoiceItem {
+    // "40 hours", or just "40" without a unit
+    pub fn quantity_label(&self) -> String {
+        match &self.unit {
+            Some(unit) => format!("{} {}", self.quantity, unit),
+            None => self.quantity.to_string(),
+        }
+    }
+}
+
+// Extra tax stacked on the main one (e.g. a city tax on top of state tax);
+// applies to the same taxable amount. `rate` is a fraction like `tax_rate`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SecondTax {
+    pub label: String,
+    pub rate: f64,
+}
+
+// One tax line as printed under the subtotal
+#[derive(Debug, Serialize, Clone)]
+pub struct TaxLine {
+    pub label: String,
+    pub rate: f64,
+    pub amount: f64,
+}
+
+// One labeled line of the bank transfer block
+#[derive(Debug, Serialize, Clone)]
+pub struct BankLine {
+    pub label: String,
+    pub value: String,
+}
+
+// Invoice-level discount, applied to the subtotal before tax
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Discount {
+    Percent(f64),
+    Flat(f64),
+}
+
+impl Discount {
+    // Accepts "10%" or a flat amount such as "50" / "$50"
+    pub fn parse(input: &str) -> Option<Discount> {
+        let input = input.trim();
+        if let Some(pct) = input.strip_suffix('%') {
+            let value: f64 = pct.trim().parse().ok()?;
+            return (value > 0.0 && value <= 100.0).then_some(Discount::Percent(value));
+        }
+        let number = input.trim_start_matches(|c: char| !c.is_ascii_digit() && c != '.');
+        let value: f64 = number.trim().parse().ok()?;
+        (value > 0.0).then_some(Discount::Flat(value))
+    }
+
+    // Discount amount for `subtotal`, never more than the subtotal itself
+    pub fn amount(&self, subtotal: f64) -> f64 {
+        let raw = match self {
+            Discount::Percent(pct) => subtotal * pct / 100.0,
+            Discount::Flat(value) => *value,
+        };
+        (raw.min(subtotal) * 100.0).round() / 100.0
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Discount::Percent(pct) => format!("Discount ({}%)", pct),
+            Discount::Flat(_) => "Discount".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SenderConfig {
+    #[serde(default)]
+    pub schema_version: u32,
     pub name: String,
     pub address1: String,
     pub address2: String,
     pub license: String,
     pub email: String,
     pub phone: String,
+    // Free-text payment details, printed when none of the bank fields below are set
+    #[serde(default)]
     pub bank_info: String,
+    // Structured wire details; each one is printed on its own labeled line
+    #[serde(default)]
+    pub bank_name: Option<String>,
+    #[serde(default)]
+    pub account_name: Option<String>,
+    #[serde(default)]
+    pub account_number: Option<String>,
+    #[serde(default)]
+    pub routing_number: Option<String>,
+    #[serde(default)]
+    pub iban: Option<String>,
+    #[serde(default)]
+    pub swift: Option<String>,
+    // Render whole amounts as "$500" instead of "$500.00"
+    #[serde(default)]
+    pub whole_dollar_amounts: bool,
+    // ISO currency code, e.g. "USD" or "EUR"
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    // Overrides the symbol derived from `currency`
+    #[serde(default)]
+    pub currency_symbol: Option<String>,
+    // strftime pattern for dates printed on invoices, e.g. "%d/%m/%Y" or "%Y-%m-%d"
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    // Name of the tax on invoices, e.g. "VAT", "GST" or "Sales Tax"
+    #[serde(default = "default_tax_label")]
+    pub tax_label: String,
+    // Item prices already include tax (e.g. EU consumer pricing); pre-selects the wizard answer
+    #[serde(default)]
+    pub tax_inclusive: bool,
+    // Round tax to cents once on the taxable total ("total") or on every line ("per_line")
+    #[serde(default)]
+    pub rounding_mode: RoundingMode,
+    // Paper size of the PDF: "Letter" or "A4"
+    #[serde(default = "default_page_size")]
+    pub page_size: String,
+    // Brand color of the title, header rule and total, e.g. "#0055aa"
+    #[serde(default = "default_accent_color")]
+    pub accent_color: String,
+    // Home country; pre-fills the Country prompt of new addresses
+    #[serde(default)]
+    pub country: Option<String>,
+    // Payment link (UPI, SEPA, PayPal...) printed as a QR code on the invoice
+    #[serde(default)]
+    pub payment_uri: Option<String>,
+    // Letterhead image; absolute or relative to the data root
+    #[serde(default)]
+    pub logo_path: Option<String>,
+    // Boilerplate terms & conditions printed under the payment details (may span lines)
+    #[serde(default)]
+    pub terms: Option<String>,
+    // Closing line of every invoice, replacing "Thank you for your business!"
+    #[serde(default)]
+    pub footer: Option<String>,
+    // Outgoing mail settings for the Send command
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    // Profile name (senders/<name>.toml); None for the single sender.toml
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+// `[smtp]` section of sender.toml. Subject/body accept the placeholders
+// {invoice_id}, {client}, {total}, {due_date} and {sender}.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub username: String,
+    // Falls back to $INVOICE_SMTP_PASSWORD, then a password prompt
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    #[serde(default = "default_email_subject")]
+    pub subject: String,
+    #[serde(default = "default_email_body")]
+    pub body: String,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_email_subject() -> String {
+    "Invoice {invoice_id} from {sender}".to_string()
+}
+
+fn default_email_body() -> String {
+    "Hello {client},\n\nPlease find attached invoice {invoice_id} for {total}, due {due_date}.\n\nThank you for your business!\n{sender}".to_string()
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_page_size() -> String {
+    "Letter".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    #[default]
+    Total,
+    PerLine,
+}
+
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+// "12345.67" -> "12,345.67"; only the whole part is grouped
+pub fn group_thousands(digits: &str) -> String {
+    let (whole, frac) = digits.split_once('.').map_or((digits, None), |(w, f)| (w, Some(f)));
+    let mut grouped = String::new();
+    for (i, c) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    match frac {
+        Some(f) => format!("{}.{}", grouped, f),
+        None => grouped,
+    }
+}
+
+pub const DEFAULT_ACCENT_COLOR: &str = "#0055aa";
+
+fn default_accent_color() -> String {
+    DEFAULT_ACCENT_COLOR.to_string()
+}
+
+// "#0055aa", "0055aa" or "#05a" -> (r, g, b)
+pub fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        6 => Some((channel(&hex[0..2])?, channel(&hex[2..4])?, channel(&hex[4..6])?)),
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
+
+fn default_tax_label() -> String {
+    "Tax".to_string()
+}
+
+pub fn default_date_format() -> String {
+    "%m/%d/%Y".to_string()
+}
+
+impl SenderConfig {
+    pub fn symbol(&self) -> String {
+        if let Some(symbol) = &self.currency_symbol {
+            return symbol.clone();
+        }
+        match self.currency.to_uppercase().as_str() {
+            "USD" | "CAD" | "AUD" | "NZD" | "MXN" => "$".to_string(),
+            "EUR" => "€".to_string(),
+            "GBP" => "£".to_string(),
+            "JPY" | "CNY" => "¥".to_string(),
+            "INR" => "₹".to_string(),
+            "CHF" => "CHF ".to_string(),
+            other => format!("{} ", other),
+        }
+    }
+
+    // terms / footer with real line breaks turned into the "\n" markup of notes; None when blank
+    pub fn footer_text(text: Option<&str>) -> Option<String> {
+        let text = text?.trim();
+        (!text.is_empty()).then(|| text.lines().map(str::trim_end).collect::<Vec<_>>().join("\\n"))
+    }
+
+    // Bank fields that are set, in print order
+    pub fn bank_details(&self) -> Vec<BankLine> {
+        [
+            ("Bank", &self.bank_name),
+            ("Account Name", &self.account_name),
+            ("Account No.", &self.account_number),
+            ("Routing No.", &self.routing_number),
+            ("IBAN", &self.iban),
+            ("SWIFT / BIC", &self.swift),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| {
+            let value = value.as_deref()?.trim();
+            (!value.is_empty()).then(|| BankLine { label: label.to_string(), value: value.to_string() })
+        })
+        .collect()
+    }
+
+    // Typst paper name for `page_size`; None when it isn't a supported size
+    pub fn paper(&self) -> Option<&'static str> {
+        match self.page_size.to_lowercase().as_str() {
+            "letter" | "us-letter" => Some("us-letter"),
+            "a4" => Some("a4"),
+            _ => None,
+        }
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    // Currency formatter shared by the invoice context and summaries
+    pub fn format_money(&self, amount: f64) -> String {
+        // Sign goes before the symbol: "-$50.00"
+        let (negative, cents) = to_cents(amount);
+        let sign = if negative { "-" } else { "" };
+        let digits = if self.whole_dollar_amounts && cents % 100 == 0 {
+            (cents / 100).to_string()
+        } else {
+            format!("{}.{:02}", cents / 100, cents % 100)
+        };
+        format!("{}{}{}", sign, self.symbol(), group_thousands(&digits))
+    }
+
+    // Check-style amount: "One thousand two hundred fifty dollars and 00/100".
+    // Rounded like format_money, so the words always match the printed total;
+    // whole amounts drop the "and 00/100" when whole_dollar_amounts is set.
+    pub fn amount_in_words(&self, amount: f64) -> String {
+        let (negative, cents_total) = to_cents(amount);
+        let currency = self.currency.to_uppercase();
+        let unit = match currency.as_str() {
+            "USD" | "CAD" | "AUD" | "NZD" => "dollars",
+            "EUR" => "euros",
+            "GBP" => "pounds",
+            "MXN" => "pesos",
+            "JPY" => "yen",
+            "INR" => "rupees",
+            "CHF" => "francs",
+            other => other,
+        };
+        let sign = if negative { "minus " } else { "" };
+        let mut words = format!("{}{} {}", sign, number_in_words(cents_total / 100), unit);
+        if !(self.whole_dollar_amounts && cents_total % 100 == 0) {
+            words.push_str(&format!(" and {:02}/100", cents_total % 100));
+        }
+        let mut chars = words.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => words,
+        }
+    }
+}
+
+// Whole cents of `amount` and whether it is negative; amounts that round to zero are never negative
+fn to_cents(amount: f64) -> (bool, u64) {
+    let cents = (amount.abs() * 100.0).round() as u64;
+    (amount < 0.0 && cents != 0, cents)
+}
+
+// 1250 -> "one thousand two hundred fifty"
+fn number_in_words(n: u64) -> String {
+    const SCALES: [&str; 5] = ["", "thousand", "million", "billion", "trillion"];
+    if n == 0 {
+        return "zero".to_string();
+    }
+
+    let mut groups = Vec::new();
+    let mut rest = n;
+    for scale in SCALES {
+        let group = rest % 1000;
+        if group > 0 {
+            let words = below_thousand_in_words(group);
+            groups.push(if scale.is_empty() { words } else { format!("{} {}", words, scale) });
+        }
+        rest /= 1000;
+        if rest == 0 {
+            break;
+        }
+    }
+    groups.reverse();
+    groups.join(" ")
+}
+
+fn below_thousand_in_words(n: u64) -> String {
+    const ONES: [&str; 20] = [
+        "", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+    let mut parts = Vec::new();
+    if n >= 100 {
+        parts.push(format!("{} hundred", ONES[(n / 100) as usize]));
+    }
+    let rest = (n % 100) as usize;
+    if rest >= 20 {
+        parts.push(match ONES[rest % 10] {
+            "" => TENS[rest / 10].to_string(),
+            one => format!("{}-{}", TENS[rest / 10], one),
+        });
+    } else if rest > 0 {
+        parts.push(ONES[rest].to_string());
+    }
+    parts.join(" ")
+}
+
+// Lifecycle status of a generated invoice, stored as `is_paid` / `is_void` in the
+// JSON sidecar. Legacy invoices carry it as a filename suffix (e.g. `HI20251214-01_main-st_PAID.typ`).
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    Unpaid,
+    Paid,
+    Void,
+}
+
+impl InvoiceStatus {
+    const ALL: [InvoiceStatus; 3] = [InvoiceStatus::Unpaid, InvoiceStatus::Paid, InvoiceStatus::Void];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Unpaid => "UNPAID",
+            InvoiceStatus::Paid => "PAID",
+            InvoiceStatus::Void => "VOID",
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Unpaid => "",
+            InvoiceStatus::Paid => "_PAID",
+            InvoiceStatus::Void => "_VOID",
+        }
+    }
+
+    pub fn is_paid(&self) -> bool {
+        *self == InvoiceStatus::Paid
+    }
+
+    pub fn is_void(&self) -> bool {
+        *self == InvoiceStatus::Void
+    }
+
+    // Status encoded in a file stem; the last suffix wins
+    pub fn from_stem(stem: &str) -> InvoiceStatus {
+        InvoiceStatus::ALL
+            .into_iter()
+            .find(|s| !s.suffix().is_empty() && stem.ends_with(s.suffix()))
+            .unwrap_or(InvoiceStatus::Unpaid)
+    }
+
+    // Stem with every status suffix removed (also cleans up legacy double suffixes)
+    pub fn base_stem(stem: &str) -> &str {
+        let mut base = stem;
+        while let Some(s) = InvoiceStatus::ALL
+            .into_iter()
+            .find(|s| !s.suffix().is_empty() && base.ends_with(s.suffix()))
+        {
+            base = &base[..base.len() - s.suffix().len()];
+        }
+        base
+    }
+
+    // Status a voided stem had before it was voided
+    pub fn before_void(stem: &str) -> InvoiceStatus {
+        match stem.strip_suffix(InvoiceStatus::Void.suffix()) {
+            Some(rest) => InvoiceStatus::from_stem(rest),
+            None => InvoiceStatus::from_stem(stem),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -52,9 +589,334 @@ pub struct InvoiceContext {
     pub client: ClientConfig,
     pub project: Project,
     pub items: Vec<InvoiceItem>,
+    // Sum of all lines before discount and tax
+    pub subtotal: f64,
+    // Sum of all tax lines, already rounded to cents
+    pub tax_amount: f64,
     pub total: f64,
     pub tax_rate: f64,
+    // Sum of the taxable lines; tax is computed on this amount only
+    pub taxable_subtotal: f64,
+    pub discount_amount: f64,
+    // Payment terms (e.g. "Net 30") and the resulting due date; empty when unset
+    pub terms: String,
+    pub due_date: String,
+    pub discount_label: String,
     pub is_paid: bool,
     pub is_void: bool,
+    // Unnumbered proof from `new --proof`: "DRAFT" watermark, no sidecar
+    pub is_draft: bool,
+    // Estimates render as "ESTIMATE" and are not counted as revenue
+    pub is_estimate: bool,
+    // Invoice ID a credit note refers to; None for regular invoices
+    pub credit_for: Option<String>,
+    // Free-text note printed in the footer
+    pub notes: Option<String>,
+    // Sender's terms & conditions and closing line, in the "\n" markup of notes
+    pub terms_and_conditions: Option<String>,
+    pub footer: Option<String>,
+    // Absolute logo path, None when unset or missing; render_typ maps it into the Typst root
+    pub logo_path: Option<String>,
     pub tax_display: String,
-}
\ No newline at end of file
+    // Sender's tax name, e.g. "VAT" -> "VAT (20%):"
+    pub tax_label: String,
+    // Structured bank details from the sender; empty falls back to bank_info
+    pub bank_details: Vec<BankLine>,
+    // Typst paper name, "us-letter" or "a4"
+    pub paper: String,
+    // Sender's accent color as "#rrggbb"
+    pub accent_color: String,
+    // Total spelled out for checks, e.g. "One hundred dollars and 00/100"
+    pub total_words: String,
+    // Every tax applied, in order; empty when no tax is added
+    pub taxes: Vec<TaxLine>,
+    // Prices include the taxes: `taxes` are the shares contained in the total
+    pub tax_inclusive: bool,
+    pub currency_symbol: String,
+    // One page is rendered per label; empty means a single unlabeled copy
+    pub copy_labels: Vec<String>,
+    // PDF document metadata (title / author / keywords)
+    pub doc_title: String,
+    pub doc_author: String,
+    pub doc_keywords: Vec<String>,
+}
+
+// One installment received against an invoice
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Payment {
+    pub amount: f64,
+    pub date: NaiveDate,
+}
+
+// Authoritative invoice data, stored as `<invoice_id>.json` next to the .typ
+// so totals and status don't depend on parsing the rendered template.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InvoiceRecord {
+    pub id: String,
+    pub date: NaiveDate,
+    pub client_id: String,
+    pub client_name: String,
+    pub project_id: String,
+    pub items: Vec<InvoiceItem>,
+    pub subtotal: f64,
+    #[serde(default)]
+    pub discount: Option<Discount>,
+    #[serde(default)]
+    pub discount_amount: f64,
+    pub tax_rate: f64,
+    pub tax_amount: f64,
+    pub tax_status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_tax: Option<SecondTax>,
+    #[serde(default)]
+    pub tax_inclusive: bool,
+    pub total: f64,
+    #[serde(default)]
+    pub terms: String,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub copy_labels: Vec<String>,
+    pub is_paid: bool,
+    pub is_void: bool,
+    // When the invoice was marked PAID (or its last installment's date)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paid_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub is_estimate: bool,
+    #[serde(default)]
+    pub credit_for: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub payments: Vec<Payment>,
+    // When the invoice was emailed via Send
+    #[serde(default)]
+    pub sent_at: Option<NaiveDateTime>,
+    // Sender profile the invoice was issued under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_profile: Option<String>,
+    // Template file under templates/ (None = invoice.tera)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+impl InvoiceRecord {
+    pub fn from_context(
+        context: &InvoiceContext,
+        client_id: &str,
+        date: NaiveDate,
+        due_date: Option<NaiveDate>,
+        tax_status: &str,
+        discount: Option<Discount>,
+    ) -> InvoiceRecord {
+        let subtotal: f64 = context.items.iter().map(|i| i.amount).sum();
+        InvoiceRecord {
+            id: context.id.clone(),
+            date,
+            client_id: client_id.to_string(),
+            client_name: context.client.name.replace("Attn:", "").trim().to_string(),
+            project_id: context.project.id.clone(),
+            items: context.items.clone(),
+            subtotal,
+            discount,
+            discount_amount: context.discount_amount,
+            tax_rate: context.tax_rate,
+            tax_amount: context.taxes.iter().map(|t| t.amount).sum(),
+            tax_status: tax_status.to_string(),
+            second_tax: None,
+            tax_inclusive: context.tax_inclusive,
+            total: context.total,
+            terms: context.terms.clone(),
+            due_date,
+            copy_labels: context.copy_labels.clone(),
+            is_paid: context.is_paid,
+            is_void: context.is_void,
+            paid_date: None,
+            is_estimate: context.is_estimate,
+            credit_for: context.credit_for.clone(),
+            notes: context.notes.clone(),
+            payments: vec![],
+            sent_at: None,
+            sender_profile: context.sender.profile.clone(),
+            template: None,
+        }
+    }
+
+    pub fn amount_paid(&self) -> f64 {
+        self.payments.iter().map(|p| p.amount).sum()
+    }
+
+    // Remaining balance, rounded to cents
+    pub fn balance(&self) -> f64 {
+        ((self.total - self.amount_paid()) * 100.0).round() / 100.0
+    }
+
+    pub fn status(&self) -> InvoiceStatus {
+        if self.is_void {
+            InvoiceStatus::Void
+        } else if self.is_paid {
+            InvoiceStatus::Paid
+        } else {
+            InvoiceStatus::Unpaid
+        }
+    }
+
+    // Voiding leaves is_paid alone so unvoid can restore the previous state
+    pub fn set_status(&mut self, status: InvoiceStatus) {
+        if !status.is_void() {
+            self.is_paid = status.is_paid();
+        }
+        if status == InvoiceStatus::Unpaid {
+            self.paid_date = None;
+        }
+        self.is_void = status.is_void();
+    }
+}
+
+// Tera context of statement.tera; amounts are pre-formatted money strings
+#[derive(Serialize)]
+pub struct StatementContext {
+    pub date: String,
+    pub sender: SenderConfig,
+    pub client: ClientConfig,
+    pub lines: Vec<StatementLine>,
+    pub total_invoiced: String,
+    pub total_paid: String,
+    pub total_outstanding: String,
+    pub paper: String,
+    pub accent_color: String,
+    pub doc_title: String,
+}
+
+// One invoice on a client statement
+#[derive(Serialize)]
+pub struct StatementLine {
+    pub date: String,
+    pub id: String,
+    pub status: String,
+    pub total: String,
+    pub paid: String,
+    pub balance: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Minimal sender.toml plus `extra` settings
+    fn sender(extra: &str) -> SenderConfig {
+        let toml = format!(
+            "name = \"Acme\"\naddress1 = \"\"\naddress2 = \"\"\nlicense = \"\"\nemail = \"\"\nphone = \"\"\n{}",
+            extra
+        );
+        toml::from_str(&toml).unwrap()
+    }
+
+    #[test]
+    fn format_money_puts_the_sign_before_the_symbol() {
+        let sender = sender("");
+        assert_eq!(sender.format_money(-50.0), "-$50.00");
+        assert_eq!(sender.format_money(-0.5), "-$0.50");
+    }
+
+    #[test]
+    fn format_money_drops_the_sign_of_amounts_that_round_to_zero() {
+        let sender = sender("");
+        assert_eq!(sender.format_money(-0.004), "$0.00");
+        assert_eq!(sender.format_money(-0.0), "$0.00");
+    }
+
+    #[test]
+    fn format_money_whole_dollar_amounts() {
+        let whole = sender("whole_dollar_amounts = true");
+        assert_eq!(whole.format_money(500.0), "$500");
+        assert_eq!(whole.format_money(499.999), "$500");
+        assert_eq!(whole.format_money(500.5), "$500.50");
+        assert_eq!(sender("").format_money(500.0), "$500.00");
+    }
+
+    #[test]
+    fn format_money_groups_thousands() {
+        let sender = sender("currency = \"EUR\"");
+        assert_eq!(sender.format_money(12345.67), "€12,345.67");
+        assert_eq!(sender.format_money(-1234567.0), "-€1,234,567.00");
+        assert_eq!(sender.format_money(999.99), "€999.99");
+    }
+
+    #[test]
+    fn status_suffix_round_trips_through_from_stem() {
+        for status in InvoiceStatus::ALL {
+            let stem = format!("INV20251214-01_main-st{}", status.suffix());
+            assert_eq!(InvoiceStatus::from_stem(&stem), status);
+            assert_eq!(InvoiceStatus::base_stem(&stem), "INV20251214-01_main-st");
+        }
+    }
+
+    #[test]
+    fn base_stem_strips_legacy_double_suffixes() {
+        assert_eq!(InvoiceStatus::base_stem("HI20251214-01_main-st_PAID_VOID"), "HI20251214-01_main-st");
+        assert_eq!(InvoiceStatus::base_stem("HI20251214-01_main-st_VOID_PAID"), "HI20251214-01_main-st");
+        // Only suffixes count, not a PAID inside the project ID
+        assert_eq!(InvoiceStatus::base_stem("HI20251214-01_PAID-st"), "HI20251214-01_PAID-st");
+    }
+
+    #[test]
+    fn before_void_restores_the_previous_status() {
+        assert_eq!(InvoiceStatus::before_void("HI20251214-01_main-st_PAID_VOID"), InvoiceStatus::Paid);
+        assert_eq!(InvoiceStatus::before_void("HI20251214-01_main-st_VOID"), InvoiceStatus::Unpaid);
+    }
+
+    #[test]
+    fn status_round_trips_through_the_sidecar_flags() {
+        let json = r#"{"id": "INV20251214-01", "date": "2025-12-14", "client_id": "acme", "client_name": "Acme",
+            "project_id": "main-st", "items": [], "subtotal": 100.0, "tax_rate": 0.0, "tax_amount": 0.0,
+            "tax_status": "Exempt", "total": 100.0, "is_paid": false, "is_void": false}"#;
+        let mut record: InvoiceRecord = serde_json::from_str(json).unwrap();
+        assert_eq!(record.status(), InvoiceStatus::Unpaid);
+
+        for status in InvoiceStatus::ALL {
+            record.set_status(status);
+            assert_eq!(record.status(), status);
+            let reloaded: InvoiceRecord = serde_json::from_str(&serde_json::to_string(&record).unwrap()).unwrap();
+            assert_eq!(reloaded.status(), status);
+        }
+
+        // Voiding a paid invoice keeps the paid flag for unvoid
+        record.set_status(InvoiceStatus::Paid);
+        record.set_status(InvoiceStatus::Void);
+        assert!(record.is_paid);
+    }
+
+
+    #[test]
+    fn number_in_words_covers_scales_and_hyphens() {
+        assert_eq!(number_in_words(0), "zero");
+        assert_eq!(number_in_words(15), "fifteen");
+        assert_eq!(number_in_words(42), "forty-two");
+        assert_eq!(number_in_words(1250), "one thousand two hundred fifty");
+        assert_eq!(number_in_words(2_000_017), "two million seventeen");
+    }
+
+    #[test]
+    fn amount_in_words_prints_cents_and_currency() {
+        let usd = sender("");
+        assert_eq!(usd.amount_in_words(1250.0), "One thousand two hundred fifty dollars and 00/100");
+        assert_eq!(usd.amount_in_words(-12.5), "Minus twelve dollars and 50/100");
+        assert_eq!(usd.amount_in_words(-0.001), "Zero dollars and 00/100");
+        assert_eq!(sender("currency = \"EUR\"").amount_in_words(3.07), "Three euros and 07/100");
+    }
+
+    #[test]
+    fn amount_in_words_matches_format_money_rounding() {
+        let whole = sender("whole_dollar_amounts = true");
+        assert_eq!(whole.format_money(499.999), "$500");
+        assert_eq!(whole.amount_in_words(499.999), "Five hundred dollars");
+        assert_eq!(whole.amount_in_words(500.5), "Five hundred dollars and 50/100");
+        // 0.995 rounds up to the next dollar in both
+        let usd = sender("");
+        assert_eq!(usd.format_money(20.995), "$21.00");
+        assert_eq!(usd.amount_in_words(20.995), "Twenty-one dollars and 00/100");
+    }
+
+}
@@ -0,0 +1,347 @@
+// Built-in PDF renderer, used when Typst isn't installed.
+// Plain Helvetica layout from the same InvoiceContext as the Typst template:
+// simpler looking, but it carries the same sender, client, items and totals.
+
+use printpdf::{BuiltinFont, Color, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point, Rgb};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::model::{parse_hex_color, InvoiceContext};
+
+// Page sizes in mm, keyed by the Typst paper name in InvoiceContext
+const LETTER: (f32, f32) = (215.9, 279.4);
+const A4: (f32, f32) = (210.0, 297.0);
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 5.5;
+
+// Column x positions of the items table
+const QTY_RIGHT: f32 = 130.0;
+const RATE_RIGHT: f32 = 160.0;
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+}
+
+// Writes top-down and starts a new page when the bottom margin is reached
+struct Cursor<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Cursor<'_> {
+    // Right edge of the text area, where amounts are aligned
+    fn right(&self) -> f32 {
+        self.width - MARGIN
+    }
+
+    fn text(&self, text: &str, size: f32, x: f32, font: &IndirectFontRef) {
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    // Built-in fonts carry no metrics here, so right alignment uses an average Helvetica glyph width
+    fn text_right(&self, text: &str, size: f32, right: f32, font: &IndirectFontRef) {
+        let width = text.chars().count() as f32 * size * 0.5 * 0.3528;
+        self.text(text, size, right - width, font);
+    }
+
+    // Fill color of the following text; None is black
+    fn color(&self, hex: Option<&str>) {
+        let (r, g, b) = hex.and_then(parse_hex_color).unwrap_or((0, 0, 0));
+        self.layer.set_fill_color(Color::Rgb(Rgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, None)));
+    }
+
+    fn rule(&self) {
+        let line = Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(self.right()), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        };
+        self.layer.set_outline_color(Color::Rgb(Rgb::new(0.87, 0.87, 0.87, None)));
+        self.layer.set_outline_thickness(1.0);
+        self.layer.add_line(line);
+    }
+
+    fn advance(&mut self, mm: f32) {
+        self.y -= mm;
+        if self.y < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(self.width), Mm(self.height), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = self.height - MARGIN;
+        }
+    }
+}
+
+pub fn render(ctx: &InvoiceContext, pdf_path: &Path) -> Result<(), String> {
+    let (width, height) = if ctx.paper == "a4" { A4 } else { LETTER };
+    let (doc, page, layer) = PdfDocument::new(&ctx.doc_title, Mm(width), Mm(height), "Layer 1");
+    let fonts = Fonts {
+        regular: doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| e.to_string())?,
+        bold: doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| e.to_string())?,
+    };
+
+    // One page (or more) per copy label, a single unlabeled copy by default
+    let labels: Vec<Option<&str>> = if ctx.copy_labels.is_empty() {
+        vec![None]
+    } else {
+        ctx.copy_labels.iter().map(|l| Some(l.as_str())).collect()
+    };
+
+    let mut layer = doc.get_page(page).get_layer(layer);
+    for (i, label) in labels.into_iter().enumerate() {
+        if i > 0 {
+            let (page, new_layer) = doc.add_page(Mm(width), Mm(height), "Layer 1");
+            layer = doc.get_page(page).get_layer(new_layer);
+        }
+        let mut cursor = Cursor { doc: &doc, layer: layer.clone(), y: height - MARGIN, width, height };
+        render_copy(&mut cursor, ctx, label, &fonts);
+    }
+
+    let file = File::create(pdf_path).map_err(|e| e.to_string())?;
+    doc.save(&mut BufWriter::new(file)).map_err(|e| e.to_string())
+}
+
+fn render_copy(c: &mut Cursor, ctx: &InvoiceContext, copy_label: Option<&str>, fonts: &Fonts) {
+    let sender = &ctx.sender;
+    let doc_label = if ctx.credit_for.is_some() {
+        "CREDIT NOTE"
+    } else if ctx.is_estimate {
+        "ESTIMATE"
+    } else {
+        "INVOICE"
+    };
+
+    if let Some(label) = copy_label {
+        c.text(label, 9.0, c.width / 2.0 - 15.0, &fonts.bold);
+        c.advance(LINE_HEIGHT);
+    }
+
+    // Header: sender on the left, document info on the right
+    let status = if ctx.is_void {
+        Some("VOID")
+    } else if ctx.is_paid {
+        Some("PAID")
+    } else if ctx.is_draft {
+        Some("DRAFT (not issued)")
+    } else {
+        None
+    };
+    c.text(&sender.name, 12.0, MARGIN, &fonts.bold);
+    c.color(Some(&ctx.accent_color));
+    c.text_right(doc_label, 20.0, c.right(), &fonts.bold);
+    c.color(None);
+    c.advance(LINE_HEIGHT * 1.5);
+
+    let mut info = vec![format!("{} #: {}", title_case(doc_label), ctx.id), format!("Date: {}", ctx.date)];
+    if let Some(credited) = &ctx.credit_for {
+        info.push(format!("Credits Invoice: {}", credited));
+    }
+    if !ctx.due_date.is_empty() {
+        info.push(format!("Due: {}", ctx.due_date));
+    }
+    if !ctx.terms.is_empty() {
+        info.push(format!("Terms: {}", ctx.terms));
+    }
+    if let Some(status) = status {
+        info.push(format!("Status: {}", status));
+    }
+
+    let sender_lines = [&sender.address1, &sender.address2, &sender.license, &sender.phone, &sender.email];
+    for row in 0..sender_lines.len().max(info.len()) {
+        if let Some(line) = sender_lines.get(row) {
+            c.text(line, 10.0, MARGIN, &fonts.regular);
+        }
+        if let Some(line) = info.get(row) {
+            c.text_right(line, 10.0, c.right(), &fonts.regular);
+        }
+        c.advance(LINE_HEIGHT);
+    }
+
+    c.advance(LINE_HEIGHT / 2.0);
+    c.rule();
+    c.advance(LINE_HEIGHT * 1.5);
+
+    // Bill To / Project columns
+    let client = &ctx.client;
+    let mut bill_to = vec![client.name.clone()];
+    if let Some(attn) = &client.attn {
+        bill_to.push(format!("Attn: {}", attn));
+    }
+    if let Some(address) = &client.billing_address {
+        bill_to.push(address.street.clone());
+        bill_to.extend(address.street2.clone());
+        if !address.city.is_empty() {
+            bill_to.push(format!("{}, {} {}", address.city, address.state, address.zip));
+        }
+        if let Some(country) = &address.country {
+            bill_to.push(country.clone());
+        }
+    }
+    if let Some(email) = &client.email {
+        bill_to.push(email.clone());
+    }
+
+    let project = &ctx.project;
+    let mut site = Vec::new();
+    if let Some(name) = &project.name {
+        site.push(name.clone());
+    }
+    site.push(project.address.street.clone());
+    site.extend(project.address.street2.clone());
+    if !project.address.city.is_empty() {
+        site.push(format!("{}, {} {}", project.address.city, project.address.state, project.address.zip));
+    }
+    if let Some(country) = &project.address.country {
+        site.push(country.clone());
+    }
+
+    let site_x = c.width / 2.0;
+    c.text("BILL TO:", 10.0, MARGIN, &fonts.bold);
+    c.text("PROJECT / SITE LOCATION:", 10.0, site_x, &fonts.bold);
+    c.advance(LINE_HEIGHT);
+    for row in 0..bill_to.len().max(site.len()) {
+        if let Some(line) = bill_to.get(row) {
+            c.text(line, 10.0, MARGIN, if row == 0 { &fonts.bold } else { &fonts.regular });
+        }
+        if let Some(line) = site.get(row) {
+            c.text(line, 10.0, site_x, &fonts.regular);
+        }
+        c.advance(LINE_HEIGHT);
+    }
+    c.advance(LINE_HEIGHT);
+
+    // Items
+    c.text("Description", 10.0, MARGIN, &fonts.bold);
+    c.text_right("Qty", 10.0, QTY_RIGHT, &fonts.bold);
+    c.text_right("Rate", 10.0, RATE_RIGHT, &fonts.bold);
+    c.text_right("Amount", 10.0, c.right(), &fonts.bold);
+    c.advance(LINE_HEIGHT / 2.0);
+    c.rule();
+    c.advance(LINE_HEIGHT);
+
+    for item in &ctx.items {
+        let amount = if item.amount == 0.0 { "No Charge".to_string() } else { sender.format_money(item.amount) };
+        c.text_right(&item.quantity_label(), 10.0, QTY_RIGHT, &fonts.regular);
+        c.text_right(&sender.format_money(item.rate), 10.0, RATE_RIGHT, &fonts.regular);
+        c.text_right(&amount, 10.0, c.right(), &fonts.regular);
+        for line in description_lines(&item.description) {
+            c.text(&line, 10.0, MARGIN, &fonts.regular);
+            c.advance(LINE_HEIGHT);
+        }
+    }
+    c.rule();
+    c.advance(LINE_HEIGHT * 1.5);
+
+    // Totals
+    let label_right = RATE_RIGHT;
+    let mut totals = vec![("Subtotal:".to_string(), sender.format_money(ctx.subtotal))];
+    if ctx.discount_amount > 0.0 {
+        totals.push((format!("{}:", ctx.discount_label), sender.format_money(-ctx.discount_amount)));
+    }
+    if ctx.taxes.is_empty() {
+        totals.push((format!("{}:", ctx.tax_label), ctx.tax_display.clone()));
+    }
+    let incl = if ctx.tax_inclusive { "Incl. " } else { "" };
+    for tax in &ctx.taxes {
+        totals.push((format!("{}{} ({}%):", incl, tax.label, (tax.rate * 100_000.0).round() / 1000.0), sender.format_money(tax.amount)));
+    }
+    for (label, value) in totals {
+        c.text_right(&label, 10.0, label_right, &fonts.regular);
+        c.text_right(&value, 10.0, c.right(), &fonts.regular);
+        c.advance(LINE_HEIGHT);
+    }
+    c.text_right("Total:", 12.0, label_right, &fonts.bold);
+    c.color(Some(&ctx.accent_color));
+    c.text_right(&sender.format_money(ctx.total), 12.0, c.right(), &fonts.bold);
+    c.color(None);
+    c.advance(LINE_HEIGHT);
+    c.text_right(&ctx.total_words, 9.0, c.right(), &fonts.regular);
+    c.advance(LINE_HEIGHT * 1.5);
+
+    // Footer
+    c.rule();
+    c.advance(LINE_HEIGHT * 1.5);
+    if let Some(notes) = &ctx.notes {
+        c.text("Notes:", 10.0, MARGIN, &fonts.bold);
+        c.advance(LINE_HEIGHT);
+        for line in description_lines(notes) {
+            c.text(&line, 9.0, MARGIN, &fonts.regular);
+            c.advance(LINE_HEIGHT);
+        }
+        c.advance(LINE_HEIGHT / 2.0);
+    }
+
+    c.text("Payment Information:", 10.0, MARGIN, &fonts.bold);
+    c.advance(LINE_HEIGHT);
+    c.text(&format!("Please make check payable to: {}.", sender.name), 9.0, MARGIN, &fonts.regular);
+    c.advance(LINE_HEIGHT);
+    c.text(&format!("Mailing Address: {}, {}", sender.address1, sender.address2), 9.0, MARGIN, &fonts.regular);
+    c.advance(LINE_HEIGHT);
+    if !ctx.bank_details.is_empty() {
+        c.text("Bank Transfer:", 9.0, MARGIN, &fonts.bold);
+        c.advance(LINE_HEIGHT);
+        for line in &ctx.bank_details {
+            c.text(&format!("{}:", line.label), 9.0, MARGIN, &fonts.regular);
+            c.text(&line.value, 9.0, MARGIN + 30.0, &fonts.regular);
+            c.advance(LINE_HEIGHT);
+        }
+    } else if !sender.bank_info.is_empty() {
+        c.text(&format!("ACH: {}", sender.bank_info), 9.0, MARGIN, &fonts.regular);
+        c.advance(LINE_HEIGHT);
+    }
+    // No image support here, so the payment link is printed instead of a QR code
+    if let Some(uri) = sender.payment_uri.as_deref().filter(|u| !u.trim().is_empty()) {
+        c.text(&format!("Pay online: {}", uri.trim()), 9.0, MARGIN, &fonts.regular);
+        c.advance(LINE_HEIGHT);
+    }
+    if let Some(terms) = &ctx.terms_and_conditions {
+        c.text("Terms & Conditions:", 10.0, MARGIN, &fonts.bold);
+        c.advance(LINE_HEIGHT);
+        for line in description_lines(terms) {
+            c.text(&line, 8.0, MARGIN, &fonts.regular);
+            c.advance(LINE_HEIGHT);
+        }
+    }
+    c.advance(LINE_HEIGHT);
+    match &ctx.footer {
+        Some(footer) => {
+            for line in description_lines(footer) {
+                c.text(&line, 8.0, MARGIN, &fonts.regular);
+                c.advance(LINE_HEIGHT);
+            }
+        }
+        None => c.text("Thank you for your business!", 8.0, c.width / 2.0 - 20.0, &fonts.regular),
+    }
+}
+
+// Same markup as the template: "\n" starts a new line, a leading "-" is a bullet
+fn description_lines(text: &str) -> Vec<String> {
+    text.split("\\n")
+        .map(|part| {
+            let part = part.trim();
+            match part.strip_prefix('-') {
+                Some(rest) => format!("    - {}", rest.trim()),
+                None => part.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn title_case(label: &str) -> String {
+    label.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_string() + &chars.as_str().to_lowercase(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}